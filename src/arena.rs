@@ -0,0 +1,86 @@
+/// A hand-designed level built around a boss fight. Layouts are drawn with
+/// `#` walls, `.` floor, `<` the way up (where the player arrives), `>` the
+/// way down, `B` the boss and any character listed in `guards` for its escort.
+pub struct Arena {
+    pub depth : i32,
+    pub boss : &'static str,
+    pub guards : &'static [(char, &'static str)],
+    pub layout : &'static [&'static str]
+}
+
+/// The deepest level of the dungeon; its boss ends the run
+pub const FINAL_DEPTH : i32 = 10;
+
+const ARENAS : [Arena; 2] = [
+    Arena{
+        depth : 5,
+        boss : "Skarrg the Goblin King",
+        guards : &[('g', "Goblin"), ('a', "Goblin Archer")],
+        layout : &[
+            "################################################",
+            "#......#########################################",
+            "#..<...#########################################",
+            "#......#########################################",
+            "###.############################################",
+            "###.####.......................................#",
+            "###.####.......................................#",
+            "###.####...##......##.......##......##.........#",
+            "###.......a##......##...g...##......##....a....#",
+            "###.####...##......##.......##......##.........#",
+            "###.####.......................................#",
+            "###.####..............g...B...g..............>.#",
+            "###.####.......................................#",
+            "###.####...##......##.......##......##.........#",
+            "###.####..a##......##...g...##......##....a....#",
+            "###.####...##......##.......##......##.........#",
+            "###.####.......................................#",
+            "###.####.......................................#",
+            "################################################"
+        ]
+    },
+    Arena{
+        depth : FINAL_DEPTH,
+        boss : "Vexarith the Ashen Wyrm",
+        guards : &[('d', "Young Drake")],
+        layout : &[
+            "##################################################",
+            "#.....############################################",
+            "#.<...############################################",
+            "#.....############################################",
+            "##.###############################################",
+            "##.#########.......................###############",
+            "##.#######...........................#############",
+            "##.#####...............................###########",
+            "##.####.......####...........####.......##########",
+            "##........................B..............#########",
+            "##.####.......####...........####.......##########",
+            "##.#####.......d.................d.....###########",
+            "##.#######...........................#############",
+            "##.#########.......................###############",
+            "##################################################"
+        ]
+    }
+];
+
+pub fn arena_at(depth : i32) -> Option<&'static Arena> {
+    ARENAS.iter().find(|arena| arena.depth == depth)
+}
+
+impl Arena {
+    /// Every cell of the layout with its map coordinates; the layout is
+    /// centred on a map of the given size
+    pub fn cells(&self, map_width : i32, map_height : i32) -> Vec<(i32, i32, char)> {
+        let width = self.layout.iter().map(|row| row.chars().count()).max().unwrap_or(0) as i32;
+        let height = self.layout.len() as i32;
+        let origin_x = (map_width - width) / 2;
+        let origin_y = (map_height - height) / 2;
+
+        let mut cells = Vec::new();
+        for (y, row) in self.layout.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                cells.push((origin_x + x as i32, origin_y + y as i32, cell));
+            }
+        }
+        cells
+    }
+}
@@ -4,6 +4,7 @@ use specs::saveload::{ Marker, ConvertSaveload };
 use specs::prelude::*;
 use specs_derive::*;
 use rltk::{ RGB, Point };
+use std::collections::HashMap;
 
 //
 /* POD (Plain Old Data) - no logic - "pure" ECS
@@ -58,6 +59,64 @@ pub struct Player {}
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AiState { Idle, Wander, Chase, Flee, Search }
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum FactionKind { Player, Goblins, Orcs, Beasts, Wildlife, Companions }
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Reaction { Attack, Ignore, Flee }
+
+impl FactionKind {
+    /// How members of this faction behave when they see a member of another
+    pub fn reaction_to(&self, other : FactionKind) -> Reaction {
+        use FactionKind::*;
+        if *self == other { return Reaction::Ignore; }
+        match (self, other) {
+            (Player, Companions) | (Companions, Player) => Reaction::Ignore,
+            (Player, _) => Reaction::Attack,
+            (Goblins, Player) | (Goblins, Companions) => Reaction::Attack,
+            (Orcs, Player) | (Orcs, Companions) | (Orcs, Wildlife) => Reaction::Attack,
+            (Beasts, Player) | (Beasts, Companions) => Reaction::Attack,
+            (Companions, Goblins) | (Companions, Orcs) | (Companions, Beasts) => Reaction::Attack,
+            (Wildlife, _) => Reaction::Flee,
+            _ => Reaction::Ignore
+        }
+    }
+
+    /// Allies stay close to the player when they have nothing better to do
+    pub fn follows_player(&self) -> bool {
+        *self == FactionKind::Companions
+    }
+
+    /// Goblins can't resist picking up anything left lying around
+    pub fn scavenges(&self) -> bool {
+        *self == FactionKind::Goblins
+    }
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Faction {
+    pub faction : FactionKind
+}
+
+// What a monster is currently doing, what it is after, and where it last saw it.
+// The target is picked afresh every turn, so it isn't saved.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MonsterMind {
+    pub state : AiState,
+    #[serde(skip)]
+    pub target : Option<Entity>,
+    pub last_seen : Option<Point>
+}
+
+impl Default for MonsterMind {
+    fn default() -> MonsterMind {
+        MonsterMind{ state : AiState::Idle, target : None, last_seen : None }
+    }
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Name {
     pub name : String
@@ -71,7 +130,72 @@ pub struct CombatStats {
     pub max_hp : i32,
     pub hp : i32,
     pub defense : i32,
-    pub power : i32
+    pub power : i32,
+    pub level : i32,
+    pub xp : i32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attribute {
+    pub base : i32,
+    pub modifiers : i32
+}
+
+impl Attribute {
+    pub fn new(base : i32) -> Attribute {
+        Attribute{ base, modifiers : 0 }
+    }
+
+    pub fn total(&self) -> i32 {
+        self.base + self.modifiers
+    }
+
+    pub fn bonus(&self) -> i32 {
+        super::gamesystem::attr_bonus(self.total())
+    }
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Attributes {
+    pub might : Attribute,
+    pub fitness : Attribute,
+    pub quickness : Attribute,
+    pub intelligence : Attribute
+}
+
+impl Attributes {
+    pub fn new(might : i32, fitness : i32, quickness : i32, intelligence : i32) -> Attributes {
+        Attributes{
+            might : Attribute::new(might),
+            fitness : Attribute::new(fitness),
+            quickness : Attribute::new(quickness),
+            intelligence : Attribute::new(intelligence)
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Skill { Melee, Ranged, Defense, Magic, Stealth, Lockpicking, Perception }
+
+// Skill ranks, plus how much each skill has been practised towards the next rank
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Skills {
+    pub skills : HashMap<Skill, i32>,
+    pub practice : HashMap<Skill, i32>
+}
+
+impl Skills {
+    pub fn new(ranks : &[(Skill, i32)]) -> Skills {
+        let mut skills = HashMap::new();
+        for (skill, rank) in ranks.iter() {
+            skills.insert(*skill, *rank);
+        }
+        Skills{ skills, practice : HashMap::new() }
+    }
+
+    pub fn rank(&self, skill : Skill) -> i32 {
+        *self.skills.get(&skill).unwrap_or(&0)
+    }
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -79,25 +203,127 @@ pub struct WantsToMelee {
     pub target : Entity
 }
 
+// Where a hit came from: whether the player dealt it, so kills can be
+// credited for experience, and who or what dealt it, so deaths can be explained
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DamageSource {
+    pub from_player : bool,
+    pub killer : Option<String>,
+    pub cause : String
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
-    pub amount : Vec<i32>
+    pub amount : Vec<(i32, DamageSource)>
 }
 
 impl SufferDamage {
-    pub fn new_damage(store : &mut WriteStorage<SufferDamage>, victim : Entity, amount : i32) {
+    pub fn new_damage(
+        store : &mut WriteStorage<SufferDamage>,
+        victim : Entity,
+        amount : i32,
+        source : DamageSource
+    ) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, source));
         } else {
-            let dmg = SufferDamage { amount : vec![amount] };
+            let dmg = SufferDamage { amount : vec![(amount, source)] };
             store.insert(victim, dmg).expect("Unable to insert damage");
         }
     }
 }
 
+// The hit that took this creature below 1 hp; only lives until delete_the_dead,
+// but saved so a game saved in between still credits the kill
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct KilledBy {
+    pub source : DamageSource
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
+// Item property: eating it fills the eater up
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesFood {}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum HungerState { WellFed, Normal, Hungry, Starving }
+
+impl HungerState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HungerState::WellFed => "Well Fed",
+            HungerState::Normal => "Normal",
+            HungerState::Hungry => "Hungry",
+            HungerState::Starving => "Starving"
+        }
+    }
+}
+
+// Counts down once per round; when it runs out the eater slips into the
+// next, hungrier state. Starving creatures take damage every round.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct HungerClock {
+    pub state : HungerState,
+    pub duration : i32
+}
+
+// How heavy an item is, in pounds. A stack weighs this much per item.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Weight {
+    pub lbs : f32
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum EncumbranceState { Unburdened, Burdened, Overloaded }
+
+impl EncumbranceState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EncumbranceState::Unburdened => "Unburdened",
+            EncumbranceState::Burdened => "Burdened",
+            EncumbranceState::Overloaded => "Overloaded"
+        }
+    }
+
+    /// Taken off both the attack roll and armor class of whoever is weighed down
+    pub fn combat_penalty(&self) -> i32 {
+        match self {
+            EncumbranceState::Unburdened => 0,
+            EncumbranceState::Burdened => 2,
+            EncumbranceState::Overloaded => 4
+        }
+    }
+}
+
+// Worked out every turn from everything an entity carries and wears
+#[derive(Component, Debug, Clone)]
+pub struct Encumbrance {
+    pub state : EncumbranceState,
+    pub carried_lbs : f32
+}
+
+// Potions and scrolls: shown by their appearance until identified
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MagicItem {}
+
+// Item property: reveals the true nature of everything the reader carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct IdentifiesItems {}
+
+// Equipment that cannot be taken off until the curse is lifted
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Cursed {}
+
+// Item property: lifts the curse from everything the reader carries or wears
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RemovesCurse {}
+
+// The item the player has to carry back up to the surface to win
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct GoalItem {}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct ProvidesHealing {
     pub heal_amount : i32
@@ -128,14 +354,123 @@ pub struct WantsToDropItem {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Consumable {}
 
+// Identical items merge into one entity that counts how many are held
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Stackable {
+    pub quantity : i32
+}
+
+// What one of an item costs to buy from a vendor
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Value {
+    pub gold : i32
+}
+
+// A pile of coins. Anyone with a purse adds it straight to that when they pick it up.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Gold {
+    pub amount : i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Purse {
+    pub gold : i32
+}
+
+// Holds items the way a backpack does: they are InBackpack with this as the owner
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Container {}
+
+// Has to be picked before it can be opened
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Locked {
+    pub difficulty : i32
+}
+
+// Opening it uses it on whoever did so, like an item - so the trap itself is
+// built from InflictsDamage, InflictsStatus and friends
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Trapped {}
+
+// Not drawn or listed until someone has spotted it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Hidden {}
+
+// Used on whatever steps onto its tile, like an item used on them
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntryTrigger {}
+
+// How hard it is to spot and to disarm
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Trap {
+    pub difficulty : i32
+}
+
+// Item property: using it sends every monster within earshot to investigate
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RaisesAlarm {
+    pub radius : i32
+}
+
+// Moved this turn, so anything underfoot may go off
+#[derive(Component, Debug, Clone)]
+pub struct EntityMoved {}
+
+// Names the drop table rolled when the owner dies
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LootTable {
+    pub table : String
+}
+
+// Buys and sells items; their stock is whatever they carry
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Vendor {}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Ranged {
     pub range : i32
 }
 
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum DamageType { Physical, Fire, Cold, Poison, Lightning }
+
+impl DamageType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DamageType::Physical => "physical",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Poison => "poison",
+            DamageType::Lightning => "lightning"
+        }
+    }
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InflictsDamage {
-    pub damage : i32
+    pub damage : i32,
+    pub damage_type : DamageType
+}
+
+// Percentage of each damage type shrugged off; negative values are vulnerabilities.
+// Found on creatures, and on equipment where it protects whoever wears it.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Resistances {
+    pub percent : HashMap<DamageType, i32>
+}
+
+impl Resistances {
+    pub fn new(percents : &[(DamageType, i32)]) -> Resistances {
+        let mut percent = HashMap::new();
+        for (damage_type, amount) in percents.iter() {
+            percent.insert(*damage_type, *amount);
+        }
+        Resistances{ percent }
+    }
+
+    pub fn against(&self, damage_type : DamageType) -> i32 {
+        *self.percent.get(&damage_type).unwrap_or(&0)
+    }
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -143,9 +478,153 @@ pub struct AreaOfEffect {
     pub radius : i32
 }
 
+// How a new application combines with one that is already running
+#[derive(PartialEq, Copy, Clone)]
+pub enum StackRule {
+    Refresh,   // keep the longer duration and the stronger magnitude
+    Extend,    // durations add up
+    Intensify  // magnitudes add up, keep the longer duration
+}
+
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum StatusKind {
+    Confusion, Poison, Regeneration, Haste, Slow, Blindness, Paralysis, StrengthBoost
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Confusion => "Confused",
+            StatusKind::Poison => "Poisoned",
+            StatusKind::Regeneration => "Regenerating",
+            StatusKind::Haste => "Hasted",
+            StatusKind::Slow => "Slowed",
+            StatusKind::Blindness => "Blind",
+            StatusKind::Paralysis => "Paralysed",
+            StatusKind::StrengthBoost => "Strong"
+        }
+    }
+
+    pub fn stack_rule(&self) -> StackRule {
+        match self {
+            StatusKind::Poison => StackRule::Intensify,
+            StatusKind::Confusion | StatusKind::Haste | StatusKind::Slow |
+            StatusKind::Blindness => StackRule::Extend,
+            StatusKind::Regeneration | StatusKind::Paralysis |
+            StatusKind::StrengthBoost => StackRule::Refresh
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActiveStatus {
+    pub kind : StatusKind,
+    pub turns : i32,
+    pub magnitude : i32
+}
+
+// Everything currently affecting an entity. Ticked by StatusEffectSystem.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct StatusEffects {
+    pub effects : Vec<ActiveStatus>
+}
+
+impl StatusEffects {
+    pub fn apply(
+        store : &mut WriteStorage<StatusEffects>,
+        target : Entity,
+        kind : StatusKind,
+        turns : i32,
+        magnitude : i32
+    ) {
+        if store.get(target).is_none() {
+            store.insert(target, StatusEffects{ effects : Vec::new() })
+                .expect("Unable to insert status effects");
+        }
+        let statuses = store.get_mut(target).unwrap();
+
+        // Haste and slow cancel each other out
+        let opposite = match kind {
+            StatusKind::Haste => Some(StatusKind::Slow),
+            StatusKind::Slow => Some(StatusKind::Haste),
+            _ => None
+        };
+        if let Some(opposite) = opposite {
+            if statuses.has(opposite) {
+                statuses.effects.retain(|s| s.kind != opposite);
+                return;
+            }
+        }
+
+        match statuses.effects.iter_mut().find(|s| s.kind == kind) {
+            None => statuses.effects.push(ActiveStatus{ kind, turns, magnitude }),
+            Some(existing) => {
+                match kind.stack_rule() {
+                    StackRule::Refresh => {
+                        existing.turns = i32::max(existing.turns, turns);
+                        existing.magnitude = i32::max(existing.magnitude, magnitude);
+                    }
+                    StackRule::Extend => existing.turns += turns,
+                    StackRule::Intensify => {
+                        existing.turns = i32::max(existing.turns, turns);
+                        existing.magnitude += magnitude;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn has(&self, kind : StatusKind) -> bool {
+        self.effects.iter().any(|s| s.kind == kind)
+    }
+
+    /// Confused or paralysed creatures lose their turn, slowed ones lose every other turn
+    pub fn prevents_action(&self, turn : i32) -> bool {
+        self.has(StatusKind::Confusion) || self.has(StatusKind::Paralysis) ||
+            (self.has(StatusKind::Slow) && turn % 2 == 0)
+    }
+}
+
+// Item property: using it applies a status to whoever it targets
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct InflictsStatus {
+    pub kind : StatusKind,
+    pub turns : i32,
+    pub magnitude : i32
+}
+
+// Item property: using it moves whoever it targets somewhere random, within
+// range tiles if there is a range, otherwise anywhere on the level
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Teleports {
+    pub range : Option<i32>
+}
+
+// Item property: using it calls up monsters around the user
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Summons {
+    pub name : String,
+    pub count : i32
+}
+
+// Item property: affects everything in a cone towards the target rather than one tile
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ConeOfEffect {}
+
+// Something a monster can do instead of moving or attacking. The ability is
+// its own entity, carrying the same effect components an item would.
 #[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct Confusion {
-    pub turns : i32
+pub struct Ability {
+    pub owner : Entity,
+    pub range : i32,
+    pub cooldown : i32,
+    pub ready_in : i32
+}
+
+// Short-lived visual feedback; never saved
+#[derive(Component, Debug, Clone)]
+pub struct ParticleLifetime {
+    pub lifetime_ms : f32
 }
 
 // Serializetion helper code
@@ -155,17 +634,56 @@ pub struct SerializeMe;
 // Special component that exists to help serialize the game data
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
-    pub map : super::map::Map
+    pub map : super::map::Map,
+    pub clock : super::gameclock::GameClock,
+    pub progress : super::progress::RunProgress
 }
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
-pub enum EquipmentSlot { Melee, Shield }
+pub enum EquipmentSlot {
+    Melee, Shield, Ranged, Head, Body, Hands, Feet, LeftRing, RightRing, Amulet
+}
+
+// Every slot, in the order they are listed on the equipment screen
+pub const EQUIPMENT_SLOTS : [EquipmentSlot; 10] = [
+    EquipmentSlot::Melee,
+    EquipmentSlot::Shield,
+    EquipmentSlot::Ranged,
+    EquipmentSlot::Head,
+    EquipmentSlot::Body,
+    EquipmentSlot::Hands,
+    EquipmentSlot::Feet,
+    EquipmentSlot::LeftRing,
+    EquipmentSlot::RightRing,
+    EquipmentSlot::Amulet
+];
+
+impl EquipmentSlot {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EquipmentSlot::Melee => "Main Hand",
+            EquipmentSlot::Shield => "Off Hand",
+            EquipmentSlot::Ranged => "Ranged",
+            EquipmentSlot::Head => "Head",
+            EquipmentSlot::Body => "Body",
+            EquipmentSlot::Hands => "Hands",
+            EquipmentSlot::Feet => "Feet",
+            EquipmentSlot::LeftRing => "Left Ring",
+            EquipmentSlot::RightRing => "Right Ring",
+            EquipmentSlot::Amulet => "Amulet"
+        }
+    }
+}
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Equippable {
     pub slot : EquipmentSlot
 }
 
+// Weapons that need both hands: equipping one empties the shield slot
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TwoHanded {}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Equipped {
     pub owner : Entity,
@@ -186,3 +704,89 @@ pub struct DefenseBonus {
 pub struct WantsToRemoveItem {
     pub item : Entity
 }
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AmmoType { Arrow, Bolt, Stone }
+
+// Found on equippable launchers, or directly on monsters that shoot without one
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct RangedWeapon {
+    pub range : i32,
+    pub damage : i32,
+    pub ammo : AmmoType
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Ammunition {
+    pub ammo : AmmoType
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToShoot {
+    pub target : Entity
+}
+
+// A step a creature would like to take; the movement system decides whether it can
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToMove {
+    pub destination : Point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply a series of statuses to one entity and return what it ends up with
+    fn applied(statuses : &[(StatusKind, i32, i32)]) -> StatusEffects {
+        let mut world = World::new();
+        world.register::<StatusEffects>();
+        let target = world.create_entity().build();
+        {
+            let mut store = world.write_storage::<StatusEffects>();
+            for (kind, turns, magnitude) in statuses.iter() {
+                StatusEffects::apply(&mut store, target, *kind, *turns, *magnitude);
+            }
+        }
+        let store = world.read_storage::<StatusEffects>();
+        store.get(target).unwrap().clone()
+    }
+
+    fn find(statuses : &StatusEffects, kind : StatusKind) -> &ActiveStatus {
+        statuses.effects.iter().find(|s| s.kind == kind).unwrap()
+    }
+
+    #[test]
+    fn refreshing_keeps_the_longer_and_stronger() {
+        let statuses = applied(&[(StatusKind::Regeneration, 10, 1), (StatusKind::Regeneration, 4, 3)]);
+        assert_eq!(statuses.effects.len(), 1);
+        let regen = find(&statuses, StatusKind::Regeneration);
+        assert_eq!((regen.turns, regen.magnitude), (10, 3));
+    }
+
+    #[test]
+    fn extending_adds_the_durations() {
+        let statuses = applied(&[(StatusKind::Confusion, 3, 0), (StatusKind::Confusion, 4, 0)]);
+        assert_eq!(find(&statuses, StatusKind::Confusion).turns, 7);
+    }
+
+    #[test]
+    fn intensifying_adds_the_magnitudes() {
+        let statuses = applied(&[(StatusKind::Poison, 3, 1), (StatusKind::Poison, 5, 2)]);
+        let poison = find(&statuses, StatusKind::Poison);
+        assert_eq!((poison.turns, poison.magnitude), (5, 3));
+    }
+
+    #[test]
+    fn haste_and_slow_cancel_out() {
+        let statuses = applied(&[(StatusKind::Haste, 5, 0), (StatusKind::Slow, 5, 0)]);
+        assert!(!statuses.has(StatusKind::Haste));
+        assert!(!statuses.has(StatusKind::Slow));
+    }
+
+    #[test]
+    fn slowed_creatures_lose_every_other_turn() {
+        let statuses = applied(&[(StatusKind::Slow, 5, 0)]);
+        assert!(statuses.prevents_action(2));
+        assert!(!statuses.prevents_action(3));
+    }
+}
@@ -1,24 +1,57 @@
 use specs::prelude::*;
+use std::collections::HashMap;
 use super::{
+    Ability,
     CombatStats,
     GameLog,
+    InBackpack,
+    KilledBy,
+    LootTable,
+    Monster,
     Name,
     Player,
+    Position,
+    progress::RunProgress,
     RunState,
-    SufferDamage
+    spawner,
+    SufferDamage,
+    xp_for_kill
 };
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (WriteStorage<'a, CombatStats>,
-                       WriteStorage<'a, SufferDamage>);
+                       WriteStorage<'a, SufferDamage>,
+                       WriteStorage<'a, KilledBy>,
+                       Entities<'a>,
+                       ReadExpect<'a, Entity>);
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (mut stats, mut damage, mut killed_by, entities, player_entity) = data;
+        let mut xp_gain = 0;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            for (amount, source) in damage.amount.iter() {
+                let was_alive = stats.hp > 0;
+                stats.hp -= amount;
+
+                if was_alive && stats.hp < 1 {
+                    killed_by.insert(entity, KilledBy{ source : source.clone() })
+                        .expect("Unable to insert killing blow");
+
+                    // Credit the player for the killing blow
+                    if source.from_player && entity != *player_entity {
+                        xp_gain += xp_for_kill(stats.level);
+                    }
+                }
+            }
+        }
+
+        if xp_gain != 0 {
+            if let Some(player_stats) = stats.get_mut(*player_entity) {
+                player_stats.xp += xp_gain;
+            }
         }
 
         damage.clear();
@@ -33,8 +66,11 @@ pub fn delete_the_dead(ecs : &mut World) {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let players = ecs.read_storage::<Player>();
         let names = ecs.read_storage::<Name>();
+        let monsters = ecs.read_storage::<Monster>();
+        let killed_by = ecs.read_storage::<KilledBy>();
         let entities = ecs.entities();
         let mut game_log = ecs.write_resource::<GameLog>();
+        let mut progress = ecs.write_resource::<RunProgress>();
         for (entity, stats) in (&entities, &combat_stats).join() {
             if stats.hp < 1 {
                 let player = players.get(entity);
@@ -46,9 +82,14 @@ pub fn delete_the_dead(ecs : &mut World) {
                                 format!("{} is dead", &victim_name.name)
                             );
                         }
+                        let by_player = killed_by.get(entity).map(|k| k.source.from_player).unwrap_or(false);
+                        if by_player && monsters.get(entity).is_some() {
+                            progress.monsters_killed += 1;
+                        }
                         dead.push(entity);
                     }
                     Some(_) => {
+                        progress.death = killed_by.get(entity).map(|k| k.source.clone());
                         let mut runstate = ecs.write_resource::<RunState>();
                         *runstate = RunState::GameOver;
                     }
@@ -57,6 +98,67 @@ pub fn delete_the_dead(ecs : &mut World) {
         }
     }
 
+    // Monsters with a drop table may leave something behind
+    let loot : Vec<(String, String, i32, i32)> = {
+        let loot_tables = ecs.read_storage::<LootTable>();
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        dead.iter()
+            .filter_map(|victim| {
+                let table = loot_tables.get(*victim)?;
+                let pos = positions.get(*victim)?;
+                let name = names.get(*victim).map_or(String::new(), |name| name.name.clone());
+                Some((table.table.clone(), name, pos.x, pos.y))
+            })
+            .collect()
+    };
+    for (table, victim, x, y) in loot.iter() {
+        spawner::spawn_loot(ecs, table, victim, *x, *y);
+    }
+
+    // Anything the dead were carrying stays with their remains, to be searched
+    let carried : Vec<(Entity, Entity)> = {
+        let entities = ecs.entities();
+        let backpack = ecs.read_storage::<InBackpack>();
+        (&entities, &backpack).join()
+            .filter(|(_item, carried)| dead.contains(&carried.owner))
+            .map(|(item, carried)| (item, carried.owner))
+            .collect()
+    };
+    let mut remains : HashMap<Entity, Entity> = HashMap::new();
+    for (item, owner) in carried {
+        let container = match remains.get(&owner) {
+            Some(container) => *container,
+            None => {
+                let (x, y, victim) = {
+                    let positions = ecs.read_storage::<Position>();
+                    let names = ecs.read_storage::<Name>();
+                    let progress = ecs.fetch::<RunProgress>();
+                    let (x, y) = match positions.get(owner) { Some(pos) => (pos.x, pos.y), None => continue };
+                    let name = names.get(owner).map_or(String::new(), |name| name.name.clone());
+                    let victim = if progress.uniques_spawned.contains(&name) { name } else { format!("the {}", name) };
+                    (x, y, victim)
+                };
+                let container = spawner::remains(ecs, x, y, &victim);
+                remains.insert(owner, container);
+                container
+            }
+        };
+        ecs.write_storage::<InBackpack>().insert(item, InBackpack{ owner : container })
+            .expect("Unable to insert backpack entry");
+    }
+
+    // Abilities die with the monster that knew them
+    {
+        let entities = ecs.entities();
+        let abilities = ecs.read_storage::<Ability>();
+        let orphaned : Vec<Entity> = (&entities, &abilities).join()
+            .filter(|(_entity, ability)| dead.contains(&ability.owner))
+            .map(|(entity, _ability)| entity)
+            .collect();
+        dead.extend(orphaned);
+    }
+
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
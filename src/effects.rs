@@ -0,0 +1,653 @@
+use rltk::{ Point, RandomNumberGenerator, RGB };
+use specs::prelude::*;
+use std::collections::VecDeque;
+use super::{
+    AiState,
+    AreaOfEffect,
+    CombatStats,
+    ConeOfEffect,
+    Consumable,
+    Cursed,
+    DamageSource,
+    DamageType,
+    Equipped,
+    GameLog,
+    hunger_system::WELL_FED_DURATION,
+    HungerClock,
+    HungerState,
+    identification::identify,
+    IdentifiesItems,
+    InBackpack,
+    InflictsDamage,
+    InflictsStatus,
+    MagicItem,
+    Map,
+    map::TileType,
+    MonsterMind,
+    Name,
+    ParticleLifetime,
+    Position,
+    practise_skill,
+    progress::RunProgress,
+    ProvidesFood,
+    ProvidesHealing,
+    RaisesAlarm,
+    Ranged,
+    RemovesCurse,
+    Renderable,
+    Resistances,
+    Skill,
+    Skills,
+    spawner,
+    Stackable,
+    StatusEffects,
+    StatusKind,
+    SufferDamage,
+    Summons,
+    Teleports,
+    Viewshed
+};
+
+/* Effects pipeline
+   Anything that wants to change the world - items, spells, traps, monster
+   abilities, combat - describes what should happen as an EffectType aimed at
+   some Targets and pushes it onto the EffectQueue resource. run_effects_queue
+   then resolves everything in one place, so a new kind of item only needs a
+   new component here rather than changes to the systems that use it. */
+
+pub enum EffectType {
+    Damage { amount : i32, damage_type : DamageType },
+    Healing { amount : i32 },
+    WellFed,
+    Status { kind : StatusKind, turns : i32, magnitude : i32 },
+    Teleport { range : Option<i32> },
+    Spawn { name : String },
+    Particle { glyph : rltk::FontCharType, fg : RGB, bg : RGB, lifespan : f32 },
+    Alarm { at : Point },
+    ItemUse { item : Entity }
+}
+
+#[derive(Clone)]
+pub enum Targets {
+    Single { target : Entity },
+    Tile { tile_idx : i32 },
+    Tiles { tiles : Vec<i32> }
+}
+
+pub struct EffectSpawner {
+    pub creator : Option<Entity>,
+    pub effect_type : EffectType,
+    pub targets : Targets
+}
+
+#[derive(Default)]
+pub struct EffectQueue {
+    queue : VecDeque<EffectSpawner>
+}
+
+impl EffectQueue {
+    pub fn add(&mut self, creator : Option<Entity>, effect_type : EffectType, targets : Targets) {
+        self.queue.push_back(EffectSpawner{ creator, effect_type, targets });
+    }
+}
+
+/// Tiles caught in a blast of the given radius, stopped by walls
+pub fn aoe_tiles(map : &Map, target : Point, radius : i32) -> Vec<i32> {
+    let mut blast_tiles = rltk::field_of_view(target, radius, map);
+    blast_tiles.retain(|p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1);
+    blast_tiles.iter().map(|p| map.xy_idx(p.x, p.y) as i32).collect()
+}
+
+/// Tiles within hearing of a noise. Unlike a blast, sound carries through walls.
+pub fn earshot_tiles(map : &Map, origin : Point, radius : i32) -> Vec<i32> {
+    let mut tiles = Vec::new();
+    for y in i32::max(0, origin.y - radius) ..= i32::min(map.height - 1, origin.y + radius) {
+        for x in i32::max(0, origin.x - radius) ..= i32::min(map.width - 1, origin.x + radius) {
+            if rltk::DistanceAlg::Pythagoras.distance2d(origin, Point::new(x, y)) <= radius as f32 {
+                tiles.push(map.xy_idx(x, y) as i32);
+            }
+        }
+    }
+    tiles
+}
+
+/// Tiles within range of the origin, inside a cone pointing at the target
+pub fn cone_tiles(map : &Map, origin : Point, target : Point, range : i32) -> Vec<i32> {
+    let aim = ((target.x - origin.x) as f32, (target.y - origin.y) as f32);
+    let aim_length = f32::sqrt(aim.0 * aim.0 + aim.1 * aim.1);
+    if aim_length == 0.0 { return Vec::new(); }
+
+    rltk::field_of_view(origin, range, map).iter()
+        .filter(|p| **p != origin)
+        .filter(|p| {
+            let offset = ((p.x - origin.x) as f32, (p.y - origin.y) as f32);
+            let length = f32::sqrt(offset.0 * offset.0 + offset.1 * offset.1);
+            // Within roughly 35 degrees either side of the aim
+            (offset.0 * aim.0 + offset.1 * aim.1) / (length * aim_length) >= 0.82
+        })
+        .map(|p| map.xy_idx(p.x, p.y) as i32)
+        .collect()
+}
+
+/// Resolve everything that has been queued, including effects queued while
+/// resolving (an item use turns into damage, damage into particles...)
+pub fn run_effects_queue(ecs : &mut World) {
+    loop {
+        let effect = ecs.write_resource::<EffectQueue>().queue.pop_front();
+        match effect {
+            None => break,
+            Some(effect) => target_applicator(ecs, &effect)
+        }
+    }
+}
+
+fn target_applicator(ecs : &mut World, effect : &EffectSpawner) {
+    if let EffectType::ItemUse{ item } = effect.effect_type {
+        item_trigger(ecs, effect.creator, item, &effect.targets);
+        return;
+    }
+
+    match &effect.targets {
+        Targets::Single{ target } => affect_entity(ecs, effect, *target),
+        Targets::Tile{ tile_idx } => affect_tile(ecs, effect, *tile_idx),
+        Targets::Tiles{ tiles } => {
+            for tile_idx in tiles.iter() {
+                affect_tile(ecs, effect, *tile_idx);
+            }
+        }
+    }
+}
+
+fn affect_tile(ecs : &mut World, effect : &EffectSpawner, tile_idx : i32) {
+    if let EffectType::Particle{ glyph, fg, bg, lifespan } = effect.effect_type {
+        spawn_particle(ecs, tile_idx, glyph, fg, bg, lifespan);
+        return;
+    }
+    if let EffectType::Spawn{ name } = &effect.effect_type {
+        spawn_monster(ecs, name, tile_idx);
+        return;
+    }
+
+    let content = ecs.fetch::<Map>().tile_content[tile_idx as usize].clone();
+    for entity in content.iter() {
+        affect_entity(ecs, effect, *entity);
+    }
+}
+
+fn affect_entity(ecs : &mut World, effect : &EffectSpawner, target : Entity) {
+    match effect.effect_type {
+        EffectType::Damage{ amount, damage_type } => inflict_damage(ecs, effect.creator, target, amount, damage_type),
+        EffectType::Healing{ amount } => heal_damage(ecs, target, amount),
+        EffectType::WellFed => feed(ecs, target),
+        EffectType::Status{ kind, turns, magnitude } => apply_status(ecs, target, kind, turns, magnitude),
+        EffectType::Teleport{ range } => teleport(ecs, target, range),
+        EffectType::Alarm{ at } => raise_alarm(ecs, target, at),
+        EffectType::Particle{ glyph, fg, bg, lifespan } => {
+            if let Some(tile_idx) = entity_tile(ecs, target) {
+                spawn_particle(ecs, tile_idx, glyph, fg, bg, lifespan);
+            }
+        }
+        EffectType::Spawn{ .. } | EffectType::ItemUse{ .. } => {}
+    }
+}
+
+fn entity_tile(ecs : &World, entity : Entity) -> Option<i32> {
+    let positions = ecs.read_storage::<Position>();
+    let map = ecs.fetch::<Map>();
+    positions.get(entity).map(|pos| map.xy_idx(pos.x, pos.y) as i32)
+}
+
+/// Total resistance to a damage type from the target itself and anything it wears
+pub fn resistance(ecs : &World, target : Entity, damage_type : DamageType) -> i32 {
+    let resistances = ecs.read_storage::<Resistances>();
+    let equipped = ecs.read_storage::<Equipped>();
+
+    let mut percent = resistances.get(target).map(|r| r.against(damage_type)).unwrap_or(0);
+    for (worn, equipped_by) in (&resistances, &equipped).join() {
+        if equipped_by.owner == target {
+            percent += worn.against(damage_type);
+        }
+    }
+    i32::min(100, percent)
+}
+
+fn inflict_damage(ecs : &mut World, creator : Option<Entity>, target : Entity, amount : i32, damage_type : DamageType) {
+    if ecs.read_storage::<CombatStats>().get(target).is_none() { return; }
+
+    let percent = resistance(ecs, target, damage_type);
+    let amount = if percent == 0 { amount } else { amount * (100 - percent) / 100 };
+    if percent != 0 {
+        let name = ecs.read_storage::<Name>().get(target).map(|n| n.name.clone()).unwrap_or_default();
+        let message = if percent > 0 {
+            format!("{} resists the {} damage, taking {} hp.", name, damage_type.name(), amount)
+        } else {
+            format!("{} is vulnerable to {} damage, taking {} hp!", name, damage_type.name(), amount)
+        };
+        ecs.fetch_mut::<GameLog>().entries.push(message);
+    }
+    if amount < 1 { return; }
+
+    let source = DamageSource{
+        from_player : creator.is_some() && creator == Some(*ecs.fetch::<Entity>()),
+        killer : creator.filter(|creator| *creator != target)
+            .and_then(|creator| ecs.read_storage::<Name>().get(creator).map(|n| n.name.clone())),
+        cause : format!("{} damage", damage_type.name())
+    };
+    SufferDamage::new_damage(&mut ecs.write_storage::<SufferDamage>(), target, amount, source);
+
+    if let Some(tile_idx) = entity_tile(ecs, target) {
+        spawn_particle(ecs, tile_idx, rltk::to_cp437('‼'), RGB::named(rltk::ORANGE), RGB::named(rltk::BLACK), 200.0);
+    }
+}
+
+fn identify_carried(ecs : &mut World, owner : Entity) {
+    let carried : Vec<String> = {
+        let names = ecs.read_storage::<Name>();
+        let magic = ecs.read_storage::<MagicItem>();
+        let backpack = ecs.read_storage::<InBackpack>();
+        (&names, &magic, &backpack).join()
+            .filter(|(_name, _magic, carried)| carried.owner == owner)
+            .map(|(name, _magic, _carried)| name.name.clone())
+            .collect()
+    };
+
+    let mut progress = ecs.write_resource::<RunProgress>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    gamelog.entries.push("You sense the true nature of what you carry.".to_string());
+    for name in carried.iter() {
+        if let Some(appearance) = identify(&mut progress, name) {
+            gamelog.entries.push(format!("The {} is a {}.", appearance, name));
+        }
+    }
+}
+
+fn lift_curses(ecs : &mut World, owner : Entity) {
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let mut cursed = ecs.write_storage::<Cursed>();
+
+    let held : Vec<Entity> = (&entities, &cursed).join()
+        .filter(|(item, _cursed)| {
+            backpack.get(*item).map(|b| b.owner == owner).unwrap_or(false) ||
+                equipped.get(*item).map(|e| e.owner == owner).unwrap_or(false)
+        })
+        .map(|(item, _cursed)| item)
+        .collect();
+    for item in held {
+        cursed.remove(item);
+    }
+}
+
+fn feed(ecs : &mut World, target : Entity) {
+    if let Some(hunger) = ecs.write_storage::<HungerClock>().get_mut(target) {
+        hunger.state = HungerState::WellFed;
+        hunger.duration = WELL_FED_DURATION;
+    }
+}
+
+fn heal_damage(ecs : &mut World, target : Entity, amount : i32) {
+    {
+        let mut combat_stats = ecs.write_storage::<CombatStats>();
+        match combat_stats.get_mut(target) {
+            None => return,
+            Some(stats) => stats.hp = i32::min(stats.max_hp, stats.hp + amount)
+        }
+    }
+
+    if let Some(tile_idx) = entity_tile(ecs, target) {
+        spawn_particle(ecs, tile_idx, rltk::to_cp437('♥'), RGB::named(rltk::GREEN), RGB::named(rltk::BLACK), 200.0);
+    }
+}
+
+fn apply_status(ecs : &mut World, target : Entity, kind : StatusKind, turns : i32, magnitude : i32) {
+    if ecs.read_storage::<CombatStats>().get(target).is_none() { return; }
+    StatusEffects::apply(&mut ecs.write_storage::<StatusEffects>(), target, kind, turns, magnitude);
+
+    if let Some(tile_idx) = entity_tile(ecs, target) {
+        spawn_particle(ecs, tile_idx, rltk::to_cp437('?'), RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), 200.0);
+    }
+}
+
+/// Monsters that aren't already busy fighting or fleeing go to investigate
+fn raise_alarm(ecs : &mut World, target : Entity, at : Point) {
+    if let Some(mind) = ecs.write_storage::<MonsterMind>().get_mut(target) {
+        if mind.state != AiState::Chase && mind.state != AiState::Flee {
+            mind.state = AiState::Search;
+            mind.last_seen = Some(at);
+        }
+    }
+}
+
+/// Move the target to a random open floor tile, within range of where it
+/// stands if a range is given
+fn teleport(ecs : &mut World, target : Entity, range : Option<i32>) {
+    let origin = match ecs.read_storage::<Position>().get(target) {
+        None => return,
+        Some(pos) => Point::new(pos.x, pos.y)
+    };
+    let destination = {
+        let mut map = ecs.fetch_mut::<Map>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let player_pos = *ecs.fetch::<Point>();
+        let mut destination = None;
+        for _try in 0 .. 100 {
+            let (x, y) = match range {
+                None => (rng.roll_dice(1, map.width - 2), rng.roll_dice(1, map.height - 2)),
+                Some(range) => (origin.x + rng.range(-range, range + 1), origin.y + rng.range(-range, range + 1))
+            };
+            if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 { continue; }
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::Floor && !map.blocked[idx] && Point::new(x, y) != player_pos {
+                destination = Some(Point::new(x, y));
+                let from = map.xy_idx(origin.x, origin.y);
+                map.blocked[from] = false;
+                map.blocked[idx] = true;
+                break;
+            }
+        }
+        destination
+    };
+    let destination = match destination {
+        None => return,
+        Some(destination) => destination
+    };
+
+    {
+        let mut positions = ecs.write_storage::<Position>();
+        match positions.get_mut(target) {
+            None => return,
+            Some(pos) => {
+                pos.x = destination.x;
+                pos.y = destination.y;
+            }
+        }
+    }
+    if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(target) {
+        viewshed.dirty = true;
+    }
+    if target == *ecs.fetch::<Entity>() {
+        *ecs.write_resource::<Point>() = destination;
+    }
+}
+
+/// Call up a monster on the tile, if nothing is in the way
+fn spawn_monster(ecs : &mut World, name : &str, tile_idx : i32) {
+    let (x, y) = {
+        let mut map = ecs.fetch_mut::<Map>();
+        let player_pos = *ecs.fetch::<Point>();
+        let (x, y) = (tile_idx % map.width, tile_idx / map.width);
+        if map.blocked[tile_idx as usize] || Point::new(x, y) == player_pos { return; }
+        map.blocked[tile_idx as usize] = true;
+        (x, y)
+    };
+    spawner::spawn_entity(ecs, name, x, y);
+}
+
+fn spawn_particle(
+    ecs : &mut World,
+    tile_idx : i32,
+    glyph : rltk::FontCharType,
+    fg : RGB,
+    bg : RGB,
+    lifespan : f32
+) {
+    let width = ecs.fetch::<Map>().width;
+    ecs.create_entity()
+        .with(Position{ x : tile_idx % width, y : tile_idx / width })
+        .with(Renderable{ glyph, fg, bg, render_order : 0 })
+        .with(ParticleLifetime{ lifetime_ms : lifespan })
+        .build();
+}
+
+/// Turn an item's components into the effects they describe
+fn item_trigger(ecs : &mut World, creator : Option<Entity>, item : Entity, targets : &Targets) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let by_player = creator == Some(player_entity);
+    let is_spell = ecs.read_storage::<Ranged>().get(item).is_some();
+
+    // Only living things are affected
+    let victims : Vec<Entity> = {
+        let map = ecs.fetch::<Map>();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let mut victims = Vec::new();
+        let mut add_tile = |tile_idx : i32| {
+            for entity in map.tile_content[tile_idx as usize].iter() {
+                if combat_stats.get(*entity).is_some() { victims.push(*entity); }
+            }
+        };
+        match targets {
+            Targets::Single{ target } => victims.push(*target),
+            Targets::Tile{ tile_idx } => add_tile(*tile_idx),
+            Targets::Tiles{ tiles } => for tile_idx in tiles.iter() { add_tile(*tile_idx) }
+        }
+        victims
+    };
+
+    // Spells aimed at nothing are not used up
+    let used = !is_spell || !victims.is_empty();
+
+    // Using something is the surest way to learn what it is
+    if by_player && used {
+        let name = ecs.read_storage::<Name>().get(item).map(|n| n.name.clone()).unwrap_or_default();
+        if ecs.read_storage::<MagicItem>().get(item).is_some() {
+            if let Some(appearance) = identify(&mut ecs.write_resource::<RunProgress>(), &name) {
+                ecs.fetch_mut::<GameLog>().entries.push(format!("The {} was a {}!", appearance, name));
+            }
+        }
+        if ecs.read_storage::<IdentifiesItems>().get(item).is_some() {
+            identify_carried(ecs, player_entity);
+        }
+    }
+
+    if used && ecs.read_storage::<RemovesCurse>().get(item).is_some() {
+        for victim in victims.iter() {
+            lift_curses(ecs, *victim);
+            if *victim == player_entity {
+                ecs.fetch_mut::<GameLog>().entries.push("You feel as if someone is watching over you.".to_string());
+            }
+        }
+    }
+
+    // Magic skill strengthens offensive scrolls
+    let mut magic_bonus = 0;
+    if let Some(creator) = creator {
+        if let Some(creator_skills) = ecs.read_storage::<Skills>().get(creator) {
+            magic_bonus = creator_skills.rank(Skill::Magic);
+        }
+    }
+
+    let mut new_effects : Vec<(EffectType, Targets)> = Vec::new();
+    {
+        let names = ecs.read_storage::<Name>();
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        let item_name = names.get(item).map(|n| n.name.clone()).unwrap_or_default();
+        let victim_name = |victim : Entity| names.get(victim).map(|n| n.name.clone()).unwrap_or_default();
+
+        if let Some(healer) = ecs.read_storage::<ProvidesHealing>().get(item) {
+            for victim in victims.iter() {
+                new_effects.push((EffectType::Healing{ amount : healer.heal_amount }, Targets::Single{ target : *victim }));
+                if by_player {
+                    gamelog.entries.push(format!("You drink the {}, healing {} hp.", item_name, healer.heal_amount));
+                }
+            }
+        }
+
+        if ecs.read_storage::<ProvidesFood>().get(item).is_some() {
+            for victim in victims.iter() {
+                new_effects.push((EffectType::WellFed, Targets::Single{ target : *victim }));
+                if *victim == player_entity {
+                    gamelog.entries.push(format!("You eat the {}.", item_name));
+                }
+            }
+        }
+
+        if let Some(damage) = ecs.read_storage::<InflictsDamage>().get(item) {
+            let amount = damage.damage + magic_bonus;
+            for victim in victims.iter() {
+                new_effects.push((
+                    EffectType::Damage{ amount, damage_type : damage.damage_type },
+                    Targets::Single{ target : *victim }
+                ));
+                if by_player {
+                    gamelog.entries.push(format!(
+                        "You use {} on {}, inflicting {} {} damage.",
+                        item_name,
+                        victim_name(*victim),
+                        amount,
+                        damage.damage_type.name()
+                    ));
+                }
+            }
+        }
+
+        if let Some(status) = ecs.read_storage::<InflictsStatus>().get(item) {
+            let turns = if is_spell { status.turns + magic_bonus / 2 } else { status.turns };
+            for victim in victims.iter() {
+                new_effects.push((
+                    EffectType::Status{ kind : status.kind, turns, magnitude : status.magnitude },
+                    Targets::Single{ target : *victim }
+                ));
+                if *victim == player_entity {
+                    gamelog.entries.push(format!("You are {}.", status.kind.name().to_lowercase()));
+                } else if by_player {
+                    gamelog.entries.push(format!(
+                        "You use {} on {}, who is now {}.",
+                        item_name,
+                        victim_name(*victim),
+                        status.kind.name().to_lowercase()
+                    ));
+                }
+            }
+        }
+
+        if let Some(teleport) = ecs.read_storage::<Teleports>().get(item) {
+            for victim in victims.iter() {
+                new_effects.push((EffectType::Teleport{ range : teleport.range }, Targets::Single{ target : *victim }));
+                if *victim == player_entity {
+                    gamelog.entries.push("The world blurs around you.".to_string());
+                }
+            }
+        }
+
+        if let Some(alarm) = ecs.read_storage::<RaisesAlarm>().get(item) {
+            let map = ecs.fetch::<Map>();
+            let positions = ecs.read_storage::<Position>();
+            for victim in victims.iter() {
+                if let Some(pos) = positions.get(*victim) {
+                    let at = Point::new(pos.x, pos.y);
+                    new_effects.push((EffectType::Alarm{ at }, Targets::Tiles{ tiles : earshot_tiles(&map, at, alarm.radius) }));
+                    gamelog.entries.push("A loud alarm rings out!".to_string());
+                }
+            }
+        }
+
+        // Summons appear on whatever open tiles were targeted
+        if let Some(summons) = ecs.read_storage::<Summons>().get(item) {
+            new_effects.push((EffectType::Spawn{ name : summons.name.clone() }, targets.clone()));
+        }
+
+        let has_area = ecs.read_storage::<AreaOfEffect>().get(item).is_some() ||
+            ecs.read_storage::<ConeOfEffect>().get(item).is_some();
+        if has_area {
+            new_effects.push((
+                EffectType::Particle{
+                    glyph : rltk::to_cp437('░'),
+                    fg : RGB::named(rltk::ORANGE),
+                    bg : RGB::named(rltk::BLACK),
+                    lifespan : 200.0
+                },
+                targets.clone()
+            ));
+        }
+    }
+
+    {
+        let mut queue = ecs.write_resource::<EffectQueue>();
+        for (effect_type, targets) in new_effects {
+            queue.add(creator, effect_type, targets);
+        }
+    }
+
+    if !used { return; }
+
+    if is_spell {
+        if let Some(creator) = creator {
+            let mut skills = ecs.write_storage::<Skills>();
+            if let Some(creator_skills) = skills.get_mut(creator) {
+                if practise_skill(creator_skills, Skill::Magic) && by_player {
+                    ecs.fetch_mut::<GameLog>().entries.push(format!(
+                        "Your {:?} skill improves to {}.",
+                        Skill::Magic,
+                        creator_skills.rank(Skill::Magic)
+                    ));
+                }
+            }
+        }
+    }
+
+    if ecs.read_storage::<Consumable>().get(item).is_some() {
+        // Using one item from a stack leaves the rest behind
+        if let Some(stack) = ecs.write_storage::<Stackable>().get_mut(item) {
+            if stack.quantity > 1 {
+                stack.quantity -= 1;
+                return;
+            }
+        }
+        ecs.entities().delete(item).expect("Delete failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EquipmentSlot;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<Resistances>();
+        world.register::<Equipped>();
+        world
+    }
+
+    #[test]
+    fn worn_resistances_add_to_innate_ones() {
+        let mut world = world();
+        let wearer = world.create_entity()
+            .with(Resistances::new(&[(DamageType::Fire, 30)]))
+            .build();
+        world.create_entity()
+            .with(Resistances::new(&[(DamageType::Fire, 25), (DamageType::Cold, 10)]))
+            .with(Equipped{ owner : wearer, slot : EquipmentSlot::LeftRing })
+            .build();
+        assert_eq!(resistance(&world, wearer, DamageType::Fire), 55);
+        assert_eq!(resistance(&world, wearer, DamageType::Cold), 10);
+        assert_eq!(resistance(&world, wearer, DamageType::Poison), 0);
+    }
+
+    #[test]
+    fn resistance_is_capped_but_vulnerability_is_not() {
+        let mut world = world();
+        let wearer = world.create_entity()
+            .with(Resistances::new(&[(DamageType::Fire, 100), (DamageType::Cold, -50)]))
+            .build();
+        world.create_entity()
+            .with(Resistances::new(&[(DamageType::Fire, 50)]))
+            .with(Equipped{ owner : wearer, slot : EquipmentSlot::Body })
+            .build();
+        assert_eq!(resistance(&world, wearer, DamageType::Fire), 100);
+        assert_eq!(resistance(&world, wearer, DamageType::Cold), -50);
+    }
+
+    #[test]
+    fn gear_in_someone_elses_hands_does_not_count() {
+        let mut world = world();
+        let wearer = world.create_entity().build();
+        let other = world.create_entity().build();
+        world.create_entity()
+            .with(Resistances::new(&[(DamageType::Lightning, 40)]))
+            .with(Equipped{ owner : other, slot : EquipmentSlot::Amulet })
+            .build();
+        assert_eq!(resistance(&world, wearer, DamageType::Lightning), 0);
+    }
+}
@@ -0,0 +1,56 @@
+use specs::prelude::*;
+use super::{
+    Attributes,
+    Encumbrance,
+    encumbrance_state,
+    EncumbranceState,
+    Equipped,
+    GameLog,
+    InBackpack,
+    Stackable,
+    Weight
+};
+
+/// Add up what everyone is carrying and wearing, and how much it slows them
+pub struct EncumbranceSystem {}
+
+impl<'a> System<'a> for EncumbranceSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       ReadStorage<'a, Attributes>,
+                       ReadStorage<'a, Weight>,
+                       ReadStorage<'a, Stackable>,
+                       ReadStorage<'a, InBackpack>,
+                       ReadStorage<'a, Equipped>,
+                       WriteStorage<'a, Encumbrance>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, player_entity, mut gamelog, attributes, weights, stacks, backpack, equipped, mut encumbrance) = data;
+
+        for (entity, attributes) in (&entities, &attributes).join() {
+            let mut carried_lbs = 0.0;
+            for (item, weight) in (&entities, &weights).join() {
+                let carried = backpack.get(item).map(|pack| pack.owner == entity).unwrap_or(false)
+                    || equipped.get(item).map(|worn| worn.owner == entity).unwrap_or(false);
+                if carried {
+                    carried_lbs += weight.lbs * stacks.get(item).map_or(1, |stack| stack.quantity) as f32;
+                }
+            }
+
+            let state = encumbrance_state(carried_lbs, attributes.might.total());
+            let previous = encumbrance.get(entity).map_or(EncumbranceState::Unburdened, |e| e.state);
+            if entity == *player_entity && state != previous {
+                let message = match state {
+                    EncumbranceState::Unburdened => "You are no longer weighed down.",
+                    EncumbranceState::Burdened => "You are burdened by everything you carry.",
+                    EncumbranceState::Overloaded => "You are overloaded and can barely move!"
+                };
+                gamelog.entries.push(message.to_string());
+            }
+            encumbrance.insert(entity, Encumbrance{ state, carried_lbs })
+                .expect("Unable to insert encumbrance");
+        }
+    }
+}
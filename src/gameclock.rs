@@ -0,0 +1,9 @@
+use serde::{ Serialize, Deserialize };
+
+// turn counts world turns (one per monster turn), player_turns counts the
+// actions the player has taken - they drift apart when hasted or slowed
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct GameClock {
+    pub turn : i32,
+    pub player_turns : i32
+}
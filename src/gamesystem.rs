@@ -0,0 +1,279 @@
+// Derived statistics - everything here is calculated from the raw attribute
+// values, so game balance can be tweaked in one place
+use rltk::RandomNumberGenerator;
+use super::{ Attributes, EncumbranceState, Skill, Skills };
+
+/// Armor class everyone has before their quickness is taken into account
+pub const BASE_ARMOR_CLASS : i32 = 8;
+
+/// Modifier granted by an attribute: 10 is average, every 2 points above or
+/// below shifts the bonus by one
+pub fn attr_bonus(value : i32) -> i32 {
+    (value - 10).div_euclid(2)
+}
+
+/// Hit points the player gains with every level, driven by fitness
+pub fn player_hp_per_level(fitness : i32) -> i32 {
+    i32::max(1, 10 + attr_bonus(fitness) * 3)
+}
+
+/// Hit point pool of the player at a given level
+pub fn player_hp_at_level(fitness : i32, level : i32) -> i32 {
+    20 + player_hp_per_level(fitness) * level
+}
+
+/// Hit points for monsters, driven by fitness and level
+pub fn npc_hp_pool(fitness : i32, level : i32) -> i32 {
+    i32::max(1, 8 + (8 + attr_bonus(fitness) * 2) * level)
+}
+
+/// Rounds between each hit point of natural healing, driven by fitness
+pub fn regen_interval(fitness : i32) -> i32 {
+    i32::max(2, 10 - attr_bonus(fitness) * 2)
+}
+
+/// Total experience needed to advance past the given level
+pub fn xp_to_next_level(level : i32) -> i32 {
+    150 * level * (level + 1)
+}
+
+/// Experience awarded for killing something of the given level
+pub fn xp_for_kill(level : i32) -> i32 {
+    100 * level
+}
+
+/// Bonus added to the attack roll
+pub fn to_hit_bonus(might : i32) -> i32 {
+    attr_bonus(might)
+}
+
+/// Bonus added to the damage of a successful hit
+pub fn damage_bonus(might : i32) -> i32 {
+    attr_bonus(might)
+}
+
+/// Number to beat on the attack roll to land a hit
+pub fn armor_class(quickness : i32) -> i32 {
+    BASE_ARMOR_CLASS + attr_bonus(quickness)
+}
+
+/// How much can be carried without being slowed down, in pounds
+pub fn carry_capacity_lbs(might : i32) -> i32 {
+    might * 10
+}
+
+/// Beyond this much, in pounds, nothing more can be picked up
+pub fn max_carry_lbs(might : i32) -> f32 {
+    carry_capacity_lbs(might) as f32 * 1.5
+}
+
+/// Carrying more than the capacity slows the carrier down; going past half as
+/// much again leaves them barely able to move
+pub fn encumbrance_state(carried_lbs : f32, might : i32) -> EncumbranceState {
+    if carried_lbs > max_carry_lbs(might) {
+        EncumbranceState::Overloaded
+    } else if carried_lbs > carry_capacity_lbs(might) as f32 {
+        EncumbranceState::Burdened
+    } else {
+        EncumbranceState::Unburdened
+    }
+}
+
+/// Who gets to go first - higher is better
+pub fn initiative_bonus(quickness : i32) -> i32 {
+    attr_bonus(quickness)
+}
+
+/// Roll off for who acts first when a fight breaks out: d20 plus initiative
+/// bonus for each side, with ties going to whoever started it
+pub fn wins_initiative(
+    rng : &mut RandomNumberGenerator,
+    attacker : Option<&Attributes>,
+    defender : Option<&Attributes>
+) -> bool {
+    let roll = |rng : &mut RandomNumberGenerator, attributes : Option<&Attributes>| {
+        rng.roll_dice(1, 20) + attributes.map_or(0, |a| initiative_bonus(a.quickness.total()))
+    };
+    roll(rng, attacker) >= roll(rng, defender)
+}
+
+/// The attribute that backs each skill
+pub fn skill_attribute_bonus(skill : Skill, attributes : &Attributes) -> i32 {
+    match skill {
+        Skill::Melee => attributes.might.bonus(),
+        Skill::Ranged | Skill::Defense | Skill::Stealth => attributes.quickness.bonus(),
+        Skill::Magic | Skill::Lockpicking | Skill::Perception => attributes.intelligence.bonus()
+    }
+}
+
+/// Roll d20 + attribute bonus + skill rank against a difficulty. A natural 1
+/// always fails and a natural 20 always succeeds. Any system that needs to
+/// test an entity's competence (attacks, locks, traps...) should go through here.
+pub fn skill_check(
+    rng : &mut RandomNumberGenerator,
+    skills : Option<&Skills>,
+    attributes : Option<&Attributes>,
+    skill : Skill,
+    difficulty : i32
+) -> bool {
+    let natural_roll = rng.roll_dice(1, 20);
+    if natural_roll == 1 { return false; }
+    if natural_roll == 20 { return true; }
+
+    let mut total = natural_roll;
+    if let Some(skills) = skills { total += skills.rank(skill); }
+    if let Some(attributes) = attributes { total += skill_attribute_bonus(skill, attributes); }
+    total >= difficulty
+}
+
+/// What a check has to beat when the other side is only watching rather than
+/// rolling: 10 plus the rank and attribute bonus they would have added
+pub fn passive_difficulty(skills : Option<&Skills>, attributes : Option<&Attributes>, skill : Skill) -> i32 {
+    10 + skills.map_or(0, |s| s.rank(skill)) + attributes.map_or(0, |a| skill_attribute_bonus(skill, a))
+}
+
+/// Uses needed to advance a skill past the given rank
+pub fn skill_practice_needed(rank : i32) -> i32 {
+    10 * (rank + 1)
+}
+
+/// Record one use of a skill, returning true if it advanced a rank
+pub fn practise_skill(skills : &mut Skills, skill : Skill) -> bool {
+    let rank = skills.rank(skill);
+    let practice = skills.practice.entry(skill).or_insert(0);
+    *practice += 1;
+    if *practice >= skill_practice_needed(rank) {
+        *practice = 0;
+        skills.skills.insert(skill, rank + 1);
+        return true;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Attribute;
+
+    #[test]
+    fn attribute_bonus_is_centred_on_ten() {
+        assert_eq!(attr_bonus(10), 0);
+        assert_eq!(attr_bonus(11), 0);
+        assert_eq!(attr_bonus(9), -1);
+        assert_eq!(attr_bonus(12), 1);
+        assert_eq!(attr_bonus(18), 4);
+        assert_eq!(attr_bonus(8), -1);
+        assert_eq!(attr_bonus(7), -2);
+        assert_eq!(attr_bonus(4), -3);
+    }
+
+    #[test]
+    fn attribute_modifiers_count_towards_the_bonus() {
+        let mut might = Attribute::new(11);
+        assert_eq!(might.bonus(), 0);
+        might.modifiers = 3;
+        assert_eq!(might.total(), 14);
+        assert_eq!(might.bonus(), 2);
+    }
+
+    #[test]
+    fn quickness_raises_armor_class() {
+        assert_eq!(armor_class(10), BASE_ARMOR_CLASS);
+        assert!(armor_class(16) > armor_class(10));
+        assert!(armor_class(6) < armor_class(10));
+    }
+
+    #[test]
+    fn the_much_quicker_side_always_goes_first() {
+        let mut rng = RandomNumberGenerator::seeded(3);
+        let quick = Attributes::new(10, 10, 40, 10);
+        let slow = Attributes::new(10, 10, 1, 10);
+        for _fight in 0 .. 100 {
+            assert!(wins_initiative(&mut rng, Some(&quick), Some(&slow)));
+            assert!(!wins_initiative(&mut rng, Some(&slow), Some(&quick)));
+        }
+    }
+
+    #[test]
+    fn each_level_needs_more_experience_than_the_last() {
+        assert_eq!(xp_to_next_level(1), 300);
+        for level in 1 .. 20 {
+            let step = xp_to_next_level(level + 1) - xp_to_next_level(level);
+            let previous_step = xp_to_next_level(level) - xp_to_next_level(level - 1);
+            assert!(step > previous_step);
+        }
+    }
+
+    #[test]
+    fn tougher_kills_are_worth_more() {
+        assert!(xp_for_kill(3) > xp_for_kill(1));
+        assert_eq!(xp_for_kill(1) * 3, xp_to_next_level(1));
+    }
+
+    #[test]
+    fn hit_points_grow_with_level_and_fitness() {
+        assert!(player_hp_at_level(10, 2) > player_hp_at_level(10, 1));
+        assert!(player_hp_at_level(16, 3) > player_hp_at_level(10, 3));
+        // Even a sickly character gains something each level
+        assert_eq!(player_hp_per_level(1), 1);
+        assert!(npc_hp_pool(1, 1) >= 1);
+    }
+
+    #[test]
+    fn natural_rolls_beat_any_difficulty() {
+        let mut rng = RandomNumberGenerator::seeded(7);
+        let master = Skills::new(&[(Skill::Melee, 50)]);
+        let rolls = 2000;
+        let impossible = (0 .. rolls).filter(|_| skill_check(&mut rng, None, None, Skill::Melee, 100)).count();
+        let trivial = (0 .. rolls).filter(|_| skill_check(&mut rng, Some(&master), None, Skill::Melee, 1)).count();
+        // Only natural 20s pass the one, and only natural 1s fail the other
+        assert!(impossible > 0 && impossible < rolls / 10);
+        assert!(trivial < rolls && trivial > rolls - rolls / 10);
+    }
+
+    #[test]
+    fn skill_rank_and_attribute_both_help() {
+        let novice = Skills::new(&[]);
+        let expert = Skills::new(&[(Skill::Lockpicking, 4)]);
+        let clever = Attributes::new(10, 10, 10, 18);
+        let successes = |skills : &Skills, attributes : Option<&Attributes>| {
+            let mut rng = RandomNumberGenerator::seeded(3);
+            (0 .. 2000).filter(|_| skill_check(&mut rng, Some(skills), attributes, Skill::Lockpicking, 15)).count()
+        };
+        assert!(successes(&expert, None) > successes(&novice, None));
+        assert!(successes(&novice, Some(&clever)) > successes(&novice, None));
+    }
+
+    #[test]
+    fn sharp_eyes_are_harder_to_sneak_past() {
+        assert_eq!(passive_difficulty(None, None, Skill::Perception), 10);
+        let watcher = Skills::new(&[(Skill::Perception, 3)]);
+        let attributes = Attributes::new(10, 10, 10, 14);
+        assert_eq!(passive_difficulty(Some(&watcher), Some(&attributes), Skill::Perception), 15);
+    }
+
+    #[test]
+    fn practice_advances_a_rank_and_starts_over() {
+        let mut skills = Skills::new(&[(Skill::Stealth, 1)]);
+        let needed = skill_practice_needed(1);
+        for _use in 1 .. needed {
+            assert!(!practise_skill(&mut skills, Skill::Stealth));
+        }
+        assert!(practise_skill(&mut skills, Skill::Stealth));
+        assert_eq!(skills.rank(Skill::Stealth), 2);
+        assert_eq!(skills.practice[&Skill::Stealth], 0);
+        assert!(skill_practice_needed(2) > needed);
+    }
+
+    #[test]
+    fn heavier_loads_burden_then_overload() {
+        let might = 10;
+        let capacity = carry_capacity_lbs(might) as f32;
+        assert_eq!(encumbrance_state(capacity, might), EncumbranceState::Unburdened);
+        assert_eq!(encumbrance_state(capacity + 1.0, might), EncumbranceState::Burdened);
+        assert_eq!(encumbrance_state(max_carry_lbs(might), might), EncumbranceState::Burdened);
+        assert_eq!(encumbrance_state(max_carry_lbs(might) + 1.0, might), EncumbranceState::Overloaded);
+        // The same load is lighter for someone stronger
+        assert_eq!(encumbrance_state(capacity + 1.0, might + 4), EncumbranceState::Unburdened);
+    }
+}
@@ -1,18 +1,45 @@
 use rltk::{ RGB, Rltk, VirtualKeyCode };
 use specs::prelude::*;
 use super::{
+    Ammunition,
+    armor_class,
+    Attributes,
+    carry_capacity_lbs,
     CombatStats,
+    damage_bonus,
+    DamageSource,
+    DamageType,
+    Encumbrance,
+    EncumbranceState,
+    EQUIPMENT_SLOTS,
+    EquipmentSlot,
     Equipped,
+    GameClock,
     GameLog,
+    Hidden,
+    HungerClock,
+    HungerState,
+    identification::display_name,
     InBackpack,
+    initiative_bonus,
     Map,
     Name,
     Player,
     Point,
     Position,
+    progress::RunProgress,
+    Purse,
     RunState,
+    Skill,
+    Skills,
+    Stackable,
     State,
-    Viewshed
+    StatusEffects,
+    StatusKind,
+    to_hit_bonus,
+    TwoHanded,
+    Viewshed,
+    xp_to_next_level
 };
 
 #[derive(PartialEq, Copy, Clone)]
@@ -28,17 +55,19 @@ fn draw_tooltips(ecs : &World, ctx : &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let entities = ecs.entities();
 
 
     let mouse_pos = ctx.mouse_pos();
     if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height { return; }
     let mut tooltip : Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
+    for (entity, _name, position, _hidden) in (&entities, &names, &positions, !&hidden).join() {
         let idx = map.xy_idx(position.x, position.y);
         if position.x == mouse_pos.0 &&
             position.y == mouse_pos.1 &&
             map.visible_tiles[idx] {
-            tooltip.push(name.name.to_string());
+            tooltip.push(display_name(ecs, entity));
         }
     }
 
@@ -150,6 +179,68 @@ pub fn draw_ui(ecs : &World, ctx : &mut Rltk) {
             RGB::named(rltk::RED),
             RGB::named(rltk::BLACK)
         );
+
+        let experience = format!(
+            " Level: {}  XP: {} / {} ",
+            stats.level,
+            stats.xp,
+            xp_to_next_level(stats.level)
+        );
+        ctx.print_color(
+            2,
+            49,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            &experience
+        );
+    }
+
+    // Active status effects along the bottom edge
+    let statuses = ecs.read_storage::<StatusEffects>();
+    for (_player, status) in (&players, &statuses).join() {
+        let mut x = 35;
+        for effect in status.effects.iter() {
+            let label = format!(" {}({}) ", effect.kind.name(), effect.turns);
+            let colour = match effect.kind {
+                StatusKind::Regeneration | StatusKind::Haste | StatusKind::StrengthBoost => rltk::GREEN,
+                _ => rltk::ORANGE
+            };
+            ctx.print_color(x, 49, RGB::named(colour), RGB::named(rltk::BLACK), &label);
+            x += label.len() as i32;
+        }
+    }
+
+    let mut right = 78;
+    let purses = ecs.read_storage::<Purse>();
+    for (_player, purse) in (&players, &purses).join() {
+        let label = format!(" Gold: {} ", purse.gold);
+        right -= label.len() as i32;
+        ctx.print_color(right, 49, RGB::named(rltk::GOLD), RGB::named(rltk::BLACK), &label);
+    }
+
+    // Hunger and encumbrance only show once they are worth knowing about
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+    for (_player, hunger) in (&players, &hunger_clocks).join() {
+        let colour = match hunger.state {
+            HungerState::WellFed => rltk::GREEN,
+            HungerState::Normal => continue,
+            HungerState::Hungry => rltk::ORANGE,
+            HungerState::Starving => rltk::RED
+        };
+        let label = format!(" {} ", hunger.state.name());
+        right -= label.len() as i32;
+        ctx.print_color(right, 49, RGB::named(colour), RGB::named(rltk::BLACK), &label);
+    }
+    let encumbrance = ecs.read_storage::<Encumbrance>();
+    for (_player, burden) in (&players, &encumbrance).join() {
+        let colour = match burden.state {
+            EncumbranceState::Unburdened => continue,
+            EncumbranceState::Burdened => rltk::ORANGE,
+            EncumbranceState::Overloaded => rltk::RED
+        };
+        let label = format!(" {} ", burden.state.name());
+        right -= label.len() as i32;
+        ctx.print_color(right, 49, RGB::named(colour), RGB::named(rltk::BLACK), &label);
     }
 
     let log = ecs.fetch::<GameLog>();
@@ -184,19 +275,20 @@ pub fn show_inventory(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Opti
         RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
     ctx.print_color(18, y - 2,
         RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Inventory");
+    draw_carried_weight(&gs.ecs, ctx, 46, y - 2);
     ctx.print_color(18, y + count as i32 + 1,
         RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
 
     let mut equippable : Vec<Entity> = Vec::new();
     let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names).join()
+    for (entity, _pack, _name) in (&entities, &backpack, &names).join()
         .filter(|item| item.1.owner == *player_entity)
     {
         ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
         ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
         ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, &display_name(&gs.ecs, entity));
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -220,6 +312,73 @@ pub fn show_inventory(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Opti
 }
 
 
+/// Look inside a container; picking something takes it like picking it up
+pub fn show_container(gs : &mut State, ctx : &mut Rltk, container : Entity) -> (ItemMenuResult, Option<Entity>) {
+    let names = gs.ecs.read_storage::<Name>();
+    let backpack = gs.ecs.read_storage::<InBackpack>();
+    let entities = gs.ecs.entities();
+
+    let contents : Vec<Entity> = (&entities, &backpack).join()
+        .filter(|(_entity, pack)| pack.owner == container)
+        .map(|(entity, _pack)| entity)
+        .collect();
+    let count = usize::max(contents.len(), 1);
+    let title = names.get(container).map(|n| n.name.clone()).unwrap_or_default();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(15, y - 2, 31, (count + 3) as i32,
+        RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y - 2,
+        RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), &title);
+    draw_carried_weight(&gs.ecs, ctx, 46, y - 2);
+    ctx.print_color(18, y + count as i32 + 1,
+        RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+
+    if contents.is_empty() {
+        ctx.print_color(21, y, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), "(empty)");
+    }
+    for (j, entity) in contents.iter().enumerate() {
+        let row = y + j as i32;
+        ctx.set(17, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, row, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
+        ctx.set(19, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+
+        ctx.print(21, row, display_name(&gs.ecs, *entity));
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(key) => {
+            match key {
+                VirtualKeyCode::Escape => { (ItemMenuResult::Cancel, None) }
+                _ => {
+                    let selection = rltk::letter_to_option(key);
+                    if selection > -1 && selection < contents.len() as i32 {
+                        return (ItemMenuResult::Selected, Some(contents[selection as usize]));
+                    }
+                    (ItemMenuResult::NoResponse, None)
+                }
+            }
+        }
+    }
+}
+
+/// Total weight carried against what can be carried, right-aligned to end at x
+fn draw_carried_weight(ecs : &World, ctx : &mut Rltk, x : i32, y : i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    let encumbrance = ecs.read_storage::<Encumbrance>();
+    let attributes = ecs.read_storage::<Attributes>();
+    if let (Some(burden), Some(attr)) = (encumbrance.get(*player_entity), attributes.get(*player_entity)) {
+        let colour = match burden.state {
+            EncumbranceState::Unburdened => rltk::WHITE,
+            EncumbranceState::Burdened => rltk::ORANGE,
+            EncumbranceState::Overloaded => rltk::RED
+        };
+        let label = format!(" {:.1}/{} lbs ", burden.carried_lbs, carry_capacity_lbs(attr.might.total()));
+        ctx.print_color(x - label.len() as i32, y, RGB::named(colour), RGB::named(rltk::BLACK), &label);
+    }
+}
+
 pub fn drop_item_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
@@ -239,14 +398,14 @@ pub fn drop_item_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Opti
 
     let mut droppable : Vec<Entity> = Vec::new();
     let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names).join()
+    for (entity, _pack, _name) in (&entities, &backpack, &names).join()
         .filter(|item| item.1.owner == *player_entity)
     {
         ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
         ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97 + j as rltk::FontCharType);
         ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, &display_name(&gs.ecs, entity));
         droppable.push(entity);
         y += 1;
         j += 1;
@@ -269,6 +428,127 @@ pub fn drop_item_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Opti
     }
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum DropQuantityResult {
+    Cancel,
+    NoResponse,
+    Adjust{ quantity : i32 },
+    Drop{ quantity : i32 }
+}
+
+pub fn drop_quantity_menu(gs : &mut State, ctx : &mut Rltk, item : Entity, quantity : i32) -> DropQuantityResult {
+    let held = match gs.ecs.read_storage::<Stackable>().get(item) {
+        None => return DropQuantityResult::Cancel,
+        Some(stack) => stack.quantity
+    };
+    let quantity = i32::min(i32::max(quantity, 1), held);
+    let name = display_name(&gs.ecs, item);
+
+    ctx.draw_box(15, 22, 40, 5, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, 22, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Drop How Many?");
+    ctx.print(17, 24, &name);
+    ctx.print_color(17, 25, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), format!("< {} of {} >", quantity, held));
+    ctx.print_color(18, 27, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK),
+        "ARROWS to change, ENTER to drop");
+
+    match ctx.key {
+        None => DropQuantityResult::NoResponse,
+        Some(key) => {
+            match key {
+                VirtualKeyCode::Escape => DropQuantityResult::Cancel,
+                VirtualKeyCode::Left |
+                VirtualKeyCode::Down => DropQuantityResult::Adjust{ quantity : i32::max(quantity - 1, 1) },
+                VirtualKeyCode::Right |
+                VirtualKeyCode::Up => DropQuantityResult::Adjust{ quantity : i32::min(quantity + 1, held) },
+                VirtualKeyCode::Return => DropQuantityResult::Drop{ quantity },
+                _ => DropQuantityResult::NoResponse
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorMode { Buy, Sell }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorResult {
+    Cancel,
+    NoResponse,
+    SwitchMode{ mode : VendorMode },
+    Buy{ item : Entity },
+    Sell{ item : Entity }
+}
+
+pub fn show_vendor(gs : &mut State, ctx : &mut Rltk, vendor : Entity, mode : VendorMode) -> VendorResult {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let names = gs.ecs.read_storage::<Name>();
+    let backpack = gs.ecs.read_storage::<InBackpack>();
+    let purses = gs.ecs.read_storage::<Purse>();
+    let entities = gs.ecs.entities();
+
+    // Buying browses the vendor's pack, selling the player's own
+    let owner = if mode == VendorMode::Buy { vendor } else { *player_entity };
+    let wares : Vec<Entity> = (&entities, &backpack, &names).join()
+        .filter(|item| item.1.owner == owner)
+        .map(|item| item.0)
+        .collect();
+    let count = wares.len();
+
+    let title = match mode {
+        VendorMode::Buy => format!("Buy from the {}", names.get(vendor).map_or("vendor", |name| name.name.as_str())),
+        VendorMode::Sell => "Sell Which Item?".to_string()
+    };
+    let gold = purses.get(*player_entity).map_or(0, |purse| purse.gold);
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(15, y - 2, 51, (count + 3) as i32,
+        RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y - 2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), &title);
+    let purse_label = format!(" {} gold ", gold);
+    ctx.print_color(66 - purse_label.len() as i32, y - 2, RGB::named(rltk::GOLD), RGB::named(rltk::BLACK), &purse_label);
+    ctx.print_color(18, y + count as i32 + 1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK),
+        "TAB to switch buy/sell, ESCAPE to leave");
+
+    for (j, item) in wares.iter().enumerate() {
+        let price = match mode {
+            VendorMode::Buy => super::trade::buy_price(&gs.ecs, *item),
+            VendorMode::Sell => super::trade::sell_price(&gs.ecs, *item)
+        };
+        let row = y + j as i32;
+        ctx.set(17, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, row, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97 + j as rltk::FontCharType);
+        ctx.set(19, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+
+        ctx.print(21, row, display_name(&gs.ecs, *item));
+        let price_label = format!("{} gold", price);
+        ctx.print_color(65 - price_label.len() as i32, row, RGB::named(rltk::GOLD), RGB::named(rltk::BLACK), &price_label);
+    }
+
+    match ctx.key {
+        None => VendorResult::NoResponse,
+        Some(key) => {
+            match key {
+                VirtualKeyCode::Escape => VendorResult::Cancel,
+                VirtualKeyCode::Tab => {
+                    let mode = if mode == VendorMode::Buy { VendorMode::Sell } else { VendorMode::Buy };
+                    VendorResult::SwitchMode{ mode }
+                }
+                _ => {
+                    let selection = rltk::letter_to_option(key);
+                    if selection > -1 && selection < count as i32 {
+                        let item = wares[selection as usize];
+                        return match mode {
+                            VendorMode::Buy => VendorResult::Buy{ item },
+                            VendorMode::Sell => VendorResult::Sell{ item }
+                        };
+                    }
+                    VendorResult::NoResponse
+                }
+            }
+        }
+    }
+}
+
 pub fn remove_item_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
@@ -288,14 +568,14 @@ pub fn remove_item_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Op
 
     let mut removable : Vec<Entity> = Vec::new();
     let mut j = 0;
-    for (entity, _pack, name) in (&entities, &equipped, &names).join()
+    for (entity, _pack, _name) in (&entities, &equipped, &names).join()
         .filter(|item| item.1.owner == *player_entity)
     {
         ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
         ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97 + j as rltk::FontCharType);
         ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, &display_name(&gs.ecs, entity));
         removable.push(entity);
         y += 1;
         j += 1;
@@ -318,6 +598,73 @@ pub fn remove_item_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Op
     }
 }
 
+pub fn show_equipment(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let names = gs.ecs.read_storage::<Name>();
+    let equipped = gs.ecs.read_storage::<Equipped>();
+    let two_handed = gs.ecs.read_storage::<TwoHanded>();
+    let entities = gs.ecs.entities();
+
+    let count = EQUIPMENT_SLOTS.len();
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(15, y - 2, 45, (count + 3) as i32,
+        RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y - 2,
+        RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Equipment - Remove Which Item?");
+    ctx.print_color(18, y + count as i32 + 1,
+        RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+
+    // A two-handed weapon also fills the off hand
+    let mut worn : Vec<Option<Entity>> = vec![None; count];
+    let mut off_hand_by : Option<Entity> = None;
+    for (entity, item, _name) in (&entities, &equipped, &names).join()
+        .filter(|item| item.1.owner == *player_entity)
+    {
+        let slot_idx = EQUIPMENT_SLOTS.iter().position(|s| *s == item.slot).unwrap();
+        worn[slot_idx] = Some(entity);
+        if two_handed.get(entity).is_some() {
+            off_hand_by = Some(entity);
+        }
+    }
+
+    for (j, slot) in EQUIPMENT_SLOTS.iter().enumerate() {
+        let row = y + j as i32;
+        ctx.set(17, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, row, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97 + j as rltk::FontCharType);
+        ctx.set(19, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+        ctx.print_color(21, row, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), slot.name());
+
+        match worn[j] {
+            Some(item) => ctx.print(33, row, &names.get(item).unwrap().name),
+            None => {
+                let mut label = "-".to_string();
+                if let (EquipmentSlot::Shield, Some(weapon)) = (*slot, off_hand_by) {
+                    label = format!("({})", names.get(weapon).unwrap().name);
+                }
+                ctx.print_color(33, row, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), label);
+            }
+        }
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(key) => {
+            match key {
+                VirtualKeyCode::Escape => { (ItemMenuResult::Cancel, None) }
+                _ => {
+                    let selection = rltk::letter_to_option(key);
+                    if selection > -1 && selection < count as i32 {
+                        if let Some(item) = worn[selection as usize] {
+                            return (ItemMenuResult::Selected, Some(item));
+                        }
+                    }
+                    (ItemMenuResult::NoResponse, None)
+                }
+            }
+        }
+    }
+}
+
 pub fn ranged_target(gs : &mut State, ctx : &mut Rltk, range : i32) -> (ItemMenuResult, Option<Point>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let player_pos = gs.ecs.fetch::<Point>();
@@ -363,6 +710,207 @@ pub fn ranged_target(gs : &mut State, ctx : &mut Rltk, range : i32) -> (ItemMenu
     return (ItemMenuResult::NoResponse, None);
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum AttributeChoice { Might, Fitness, Quickness, Intelligence }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LevelUpResult {
+    NoSelection,
+    Selected{ attribute : AttributeChoice }
+}
+
+pub fn level_up_menu(gs : &mut State, ctx : &mut Rltk) -> LevelUpResult {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let attributes = gs.ecs.read_storage::<Attributes>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+
+    ctx.draw_box(15, 16, 40, 9, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    if let Some(stats) = combat_stats.get(*player_entity) {
+        ctx.print_color(18, 16, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK),
+            format!("Level {} reached!", stats.level + 1));
+    }
+    ctx.print(17, 18, "Choose an attribute to raise:");
+
+    let choices = [
+        (AttributeChoice::Might, "Might"),
+        (AttributeChoice::Fitness, "Fitness"),
+        (AttributeChoice::Quickness, "Quickness"),
+        (AttributeChoice::Intelligence, "Intelligence")
+    ];
+    if let Some(attr) = attributes.get(*player_entity) {
+        let values = [attr.might.total(), attr.fitness.total(), attr.quickness.total(), attr.intelligence.total()];
+        for (j, (_choice, label)) in choices.iter().enumerate() {
+            let y = 20 + j as i32;
+            ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+            ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97 + j as rltk::FontCharType);
+            ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+            ctx.print(21, y, format!("{} ({})", label, values[j]));
+        }
+    }
+
+    match ctx.key {
+        None => LevelUpResult::NoSelection,
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < choices.len() as i32 {
+                return LevelUpResult::Selected{ attribute : choices[selection as usize].0 };
+            }
+            LevelUpResult::NoSelection
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CharacterSheetResult { NoResponse, Cancel }
+
+pub fn show_character_sheet(gs : &mut State, ctx : &mut Rltk) -> CharacterSheetResult {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let attributes = gs.ecs.read_storage::<Attributes>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let skills = gs.ecs.read_storage::<Skills>();
+
+    ctx.draw_box(15, 9, 40, 28, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, 9,
+        RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Character Sheet");
+    ctx.print_color(18, 37,
+        RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to close");
+
+    if let Some(attr) = attributes.get(*player_entity) {
+        let rows = [
+            ("Might", &attr.might),
+            ("Fitness", &attr.fitness),
+            ("Quickness", &attr.quickness),
+            ("Intelligence", &attr.intelligence)
+        ];
+        let mut y = 11;
+        for (label, attribute) in rows.iter() {
+            ctx.print_color(17, y, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), label);
+            ctx.print(32, y, format!("{:>3}", attribute.total()));
+            ctx.print(37, y, format!("({:+})", attribute.bonus()));
+            y += 1;
+        }
+
+        y += 1;
+        if let Some(stats) = combat_stats.get(*player_entity) {
+            ctx.print_color(17, y, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), "Hit Points");
+            ctx.print(32, y, format!("{} / {}", stats.hp, stats.max_hp));
+            y += 1;
+        }
+        let derived = [
+            ("To Hit", format!("{:+}", to_hit_bonus(attr.might.total()))),
+            ("Damage", format!("{:+}", damage_bonus(attr.might.total()))),
+            ("Armor Class", format!("{}", armor_class(attr.quickness.total()))),
+            ("Initiative", format!("{:+}", initiative_bonus(attr.quickness.total()))),
+            ("Carry", format!("{} lbs", carry_capacity_lbs(attr.might.total())))
+        ];
+        for (label, value) in derived.iter() {
+            ctx.print_color(17, y, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), label);
+            ctx.print(32, y, value);
+            y += 1;
+        }
+
+        y += 1;
+        if let Some(skills) = skills.get(*player_entity) {
+            let all_skills = [Skill::Melee, Skill::Ranged, Skill::Defense, Skill::Magic, Skill::Stealth, Skill::Lockpicking, Skill::Perception];
+            for skill in all_skills.iter() {
+                ctx.print_color(17, y, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), format!("{:?}", skill));
+                ctx.print(32, y, format!("{:>3}", skills.rank(*skill)));
+                y += 1;
+            }
+        }
+
+        // Only list damage types the player's gear actually changes
+        y += 1;
+        let all_types = [DamageType::Physical, DamageType::Fire, DamageType::Cold, DamageType::Poison, DamageType::Lightning];
+        for damage_type in all_types.iter() {
+            let percent = super::resistance(&gs.ecs, *player_entity, *damage_type);
+            if percent == 0 { continue; }
+            let label = format!("{:?} Resist", damage_type);
+            ctx.print_color(17, y, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), label);
+            ctx.print(32, y, format!("{:+}%", percent));
+            y += 1;
+        }
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) |
+        Some(VirtualKeyCode::C) => CharacterSheetResult::Cancel,
+        _ => CharacterSheetResult::NoResponse
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum FireTargetResult {
+    Cancel,
+    NoResponse,
+    Cycle{ target : Entity },
+    Fire{ target : Entity }
+}
+
+pub fn fire_target(gs : &mut State, ctx : &mut Rltk, target : Entity) -> FireTargetResult {
+    let weapon = match super::player::player_ranged_weapon(&gs.ecs) {
+        None => return FireTargetResult::Cancel,
+        Some(weapon) => weapon
+    };
+    let targets = super::player::ranged_targets(&gs.ecs, weapon.range);
+    if targets.is_empty() { return FireTargetResult::Cancel; }
+
+    // The current target may have died or moved out of range since the last frame
+    let target = if targets.contains(&target) { target } else { targets[0] };
+
+    let player_pos = gs.ecs.fetch::<Point>();
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let positions = gs.ecs.read_storage::<Position>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let names = gs.ecs.read_storage::<Name>();
+    let backpack = gs.ecs.read_storage::<InBackpack>();
+    let ammunition = gs.ecs.read_storage::<Ammunition>();
+    let stacks = gs.ecs.read_storage::<Stackable>();
+    let map = gs.ecs.fetch::<Map>();
+
+    let ammo_left : i32 = (&backpack, &ammunition, &stacks).join()
+        .filter(|(pack, ammo, _stack)| pack.owner == *player_entity && ammo.ammo == weapon.ammo)
+        .map(|(_pack, _ammo, stack)| stack.quantity)
+        .sum();
+
+    ctx.print_color(5, 0, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK),
+        format!("Fire at: {}   {:?}s left: {}", names.get(target).unwrap().name, weapon.ammo, ammo_left));
+    ctx.print_color(5, 1, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK),
+        "TAB to cycle targets, F or ENTER to fire, ESCAPE to cancel");
+
+    // Show where the shot would actually go
+    if let Some(target_pos) = positions.get(target) {
+        let (path, hit) = super::ranged_combat_system::line_of_fire(
+            &map, &combat_stats, *player_pos, Point::new(target_pos.x, target_pos.y)
+        );
+        for tile in path.iter() {
+            ctx.set_bg(tile.x, tile.y, RGB::named(rltk::BLUE));
+        }
+        if let Some(hit) = hit {
+            if let Some(hit_pos) = positions.get(hit) {
+                let colour = if hit == target { rltk::CYAN } else { rltk::RED };
+                ctx.set_bg(hit_pos.x, hit_pos.y, RGB::named(colour));
+            }
+        }
+    }
+
+    match ctx.key {
+        None => FireTargetResult::NoResponse,
+        Some(key) => {
+            match key {
+                VirtualKeyCode::Escape => FireTargetResult::Cancel,
+                VirtualKeyCode::Tab => {
+                    let current = targets.iter().position(|t| *t == target).unwrap();
+                    FireTargetResult::Cycle{ target : targets[(current + 1) % targets.len()] }
+                }
+                VirtualKeyCode::F |
+                VirtualKeyCode::Return => FireTargetResult::Fire{ target },
+                _ => FireTargetResult::NoResponse
+            }
+        }
+    }
+}
+
 pub fn main_menu(gs : &mut State, ctx : &mut Rltk) -> MainMenuResult {
     let save_exists = super::saveload_system::does_save_exist();
     let runstate = gs.ecs.fetch::<RunState>();
@@ -432,40 +980,82 @@ pub fn main_menu(gs : &mut State, ctx : &mut Rltk) -> MainMenuResult {
 #[derive(PartialEq, Copy, Clone)]
 pub enum GameOverResult { NoSelection, QuitToMenu }
 
-pub fn game_over(ctx : &mut Rltk) -> GameOverResult {
+/// Statistics shared by both endings
+fn draw_run_summary(ecs : &World, ctx : &mut Rltk, y : i32) {
+    let progress = ecs.fetch::<RunProgress>();
+    let clock = ecs.fetch::<GameClock>();
+    let player_entity = ecs.fetch::<Entity>();
+    let level = ecs.read_storage::<CombatStats>().get(*player_entity).map(|s| s.level).unwrap_or(1);
+
+    let lines = [
+        format!("Character level:       {:>6}", level),
+        format!("Deepest level reached: {:>6}", progress.deepest_depth),
+        format!("Monsters slain:        {:>6}", progress.monsters_killed),
+        format!("Turns taken:           {:>6}", clock.player_turns)
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        ctx.print_color_centered(y + i as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), line);
+    }
+}
+
+pub fn game_over(ecs : &World, ctx : &mut Rltk) -> GameOverResult {
     ctx.print_color_centered(
         15,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
         "Your journey has ended!"
     );
+
+    let progress = ecs.fetch::<RunProgress>();
+    let cause = match &progress.death {
+        // Uniques are known by name, everything else is "the goblin"
+        Some(DamageSource{ killer : Some(killer), cause, .. }) if progress.uniques_spawned.contains(killer) => {
+            format!("You were slain by {}, taking {}.", killer, cause)
+        }
+        Some(DamageSource{ killer : Some(killer), cause, .. }) => format!("You were slain by the {}, taking {}.", killer, cause),
+        Some(DamageSource{ killer : None, cause, .. }) => format!("You succumbed to {}.", cause),
+        None => "You died.".to_string()
+    };
+    ctx.print_color_centered(17, RGB::named(rltk::RED), RGB::named(rltk::BLACK), &cause);
+    draw_run_summary(ecs, ctx, 19);
+
     ctx.print_color_centered(
-        17,
-        RGB::named(rltk::WHITE),
+        24,
+        RGB::named(rltk::MAGENTA),
         RGB::named(rltk::BLACK),
-        "One day, we'll tell you all about how you did."
+        "Press ENTER to return to the main menu."
     );
+
+    match ctx.key {
+        Some(VirtualKeyCode::Return) => GameOverResult::QuitToMenu,
+        _ => GameOverResult::NoSelection
+    }
+}
+
+pub fn victory(ecs : &World, ctx : &mut Rltk) -> GameOverResult {
     ctx.print_color_centered(
-        18,
+        15,
+        RGB::named(rltk::GOLD),
+        RGB::named(rltk::BLACK),
+        "You have escaped with the Amulet of the Ashen Deep!"
+    );
+    ctx.print_color_centered(
+        17,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
-        "This day, sadly, is not in this chapter.."
+        "The depths fall quiet, and your name passes into legend."
     );
+    draw_run_summary(ecs, ctx, 19);
 
     ctx.print_color_centered(
-        20,
+        24,
         RGB::named(rltk::MAGENTA),
         RGB::named(rltk::BLACK),
         "Press ENTER to return to the main menu."
     );
 
     match ctx.key {
-        None => GameOverResult::NoSelection,
-        Some(key) => {
-            match key {
-                VirtualKeyCode::Return => GameOverResult::QuitToMenu,
-                _ => return GameOverResult::NoSelection
-            }
-        }
+        Some(VirtualKeyCode::Return) => GameOverResult::QuitToMenu,
+        _ => GameOverResult::NoSelection
     }
 }
@@ -0,0 +1,63 @@
+use specs::prelude::*;
+use super::{
+    DamageSource,
+    GameLog,
+    HungerClock,
+    HungerState,
+    RunState,
+    SufferDamage
+};
+
+/// Rounds spent in each state before moving on to the next
+pub const WELL_FED_DURATION : i32 = 20;
+const NORMAL_DURATION : i32 = 200;
+const HUNGRY_DURATION : i32 = 200;
+
+pub struct HungerSystem {}
+
+impl<'a> System<'a> for HungerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, RunState>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       WriteStorage<'a, HungerClock>,
+                       WriteStorage<'a, SufferDamage>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, runstate, player_entity, mut gamelog, mut hunger_clocks, mut inflict_damage) = data;
+
+        // Hunger grows once per round, when the monsters move
+        if *runstate != RunState::MonsterTurn { return; }
+
+        for (entity, clock) in (&entities, &mut hunger_clocks).join() {
+            let is_player = entity == *player_entity;
+
+            if clock.state == HungerState::Starving {
+                SufferDamage::new_damage(&mut inflict_damage, entity, 1, DamageSource{
+                    from_player : false,
+                    killer : None,
+                    cause : "starvation".to_string()
+                });
+                if is_player {
+                    gamelog.entries.push("Your hunger pangs are getting painful! You suffer 1 hp damage.".to_string());
+                }
+                continue;
+            }
+
+            clock.duration -= 1;
+            if clock.duration > 0 { continue; }
+
+            let (state, duration, message) = match clock.state {
+                HungerState::WellFed => (HungerState::Normal, NORMAL_DURATION, "You are no longer well fed."),
+                HungerState::Normal => (HungerState::Hungry, HUNGRY_DURATION, "You are hungry."),
+                _ => (HungerState::Starving, 0, "You are starving!")
+            };
+            clock.state = state;
+            clock.duration = duration;
+            if is_player {
+                gamelog.entries.push(message.to_string());
+            }
+        }
+    }
+}
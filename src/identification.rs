@@ -0,0 +1,147 @@
+use rltk::{ RandomNumberGenerator, RGB };
+use serde::{ Serialize, Deserialize };
+use specs::prelude::*;
+use super::{ MagicItem, Name, progress::RunProgress, Renderable, Stackable };
+
+/// How an unidentified kind of item looks for the rest of the run
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Appearance {
+    pub description : String,
+    pub fg : RGB
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MagicKind { Potion, Scroll }
+
+const POTION_ADJECTIVES : [&str; 8] = [
+    "bubbling", "murky", "fizzing", "cloudy", "glowing", "smoking", "viscous", "sparkling"
+];
+
+const POTION_COLOURS : [(&str, (u8, u8, u8)); 10] = [
+    ("red", rltk::RED), ("blue", rltk::BLUE), ("green", rltk::GREEN), ("purple", rltk::PURPLE),
+    ("yellow", rltk::YELLOW), ("orange", rltk::ORANGE), ("pink", rltk::PINK), ("silver", rltk::SILVER),
+    ("brown", rltk::BROWN1), ("white", rltk::WHITE)
+];
+
+const SCROLL_SYLLABLES : [&str; 12] = [
+    "ka", "zu", "mor", "eth", "vel", "qua", "rin", "dol", "xa", "bri", "nok", "ul"
+];
+
+fn new_appearance(progress : &RunProgress, rng : &mut RandomNumberGenerator, kind : MagicKind) -> Appearance {
+    loop {
+        let appearance = match kind {
+            MagicKind::Potion => {
+                let adjective = POTION_ADJECTIVES[rng.range(0, POTION_ADJECTIVES.len() as i32) as usize];
+                let (colour, fg) = POTION_COLOURS[rng.range(0, POTION_COLOURS.len() as i32) as usize];
+                Appearance{
+                    description : format!("{} {} potion", adjective, colour),
+                    fg : RGB::named(fg)
+                }
+            }
+            MagicKind::Scroll => {
+                let mut label = String::new();
+                for _syllable in 0 .. rng.range(2, 4) {
+                    label += SCROLL_SYLLABLES[rng.range(0, SCROLL_SYLLABLES.len() as i32) as usize];
+                }
+                Appearance{
+                    description : format!("scroll labeled {}", label.to_uppercase()),
+                    fg : RGB::named(rltk::WHEAT)
+                }
+            }
+        };
+
+        // Two potions of the same colour would give the game away
+        let taken = progress.appearances.values().any(|other| {
+            other.description == appearance.description ||
+                (kind == MagicKind::Potion && other.fg == appearance.fg)
+        });
+        if !taken { return appearance; }
+    }
+}
+
+/// Mark a freshly spawned item as magical and dress it in its kind's
+/// appearance for this run, rolling one the first time the kind is seen
+pub fn disguise(ecs : &mut World, item : Entity, kind : MagicKind) {
+    let name = ecs.read_storage::<Name>().get(item).map(|n| n.name.clone()).unwrap_or_default();
+    let fg = {
+        let mut progress = ecs.write_resource::<RunProgress>();
+        if !progress.appearances.contains_key(&name) {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            let appearance = new_appearance(&progress, &mut rng, kind);
+            progress.appearances.insert(name.clone(), appearance);
+        }
+        progress.appearances[&name].fg
+    };
+
+    ecs.write_storage::<MagicItem>().insert(item, MagicItem{}).expect("Unable to insert magic item");
+    if let Some(renderable) = ecs.write_storage::<Renderable>().get_mut(item) {
+        renderable.fg = fg;
+    }
+}
+
+/// What the player calls an item: its real name once identified, otherwise how it looks
+pub fn known_name(progress : &RunProgress, name : &str, is_magic : bool) -> String {
+    if is_magic && !progress.identified.contains(name) {
+        if let Some(appearance) = progress.appearances.get(name) {
+            return appearance.description.clone();
+        }
+    }
+    name.to_string()
+}
+
+pub fn display_name(ecs : &World, entity : Entity) -> String {
+    let names = ecs.read_storage::<Name>();
+    let magic = ecs.read_storage::<MagicItem>();
+    let stacks = ecs.read_storage::<Stackable>();
+    let name = match names.get(entity) {
+        None => return String::new(),
+        Some(name) => known_name(&ecs.fetch::<RunProgress>(), &name.name, magic.get(entity).is_some())
+    };
+    match stacks.get(entity) {
+        Some(stack) if stack.quantity > 1 => format!("{} (x{})", name, stack.quantity),
+        _ => name
+    }
+}
+
+/// Learn what a kind of item really is. Returns how it looked if it was
+/// unknown until now.
+pub fn identify(progress : &mut RunProgress, name : &str) -> Option<String> {
+    if progress.identified.contains(name) { return None; }
+    progress.identified.insert(name.to_string());
+    progress.appearances.get(name).map(|appearance| appearance.description.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_items_go_by_their_looks_until_identified() {
+        let mut progress = RunProgress::default();
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let appearance = new_appearance(&progress, &mut rng, MagicKind::Potion);
+        let looks = appearance.description.clone();
+        progress.appearances.insert("Health Potion".to_string(), appearance);
+
+        assert_eq!(known_name(&progress, "Health Potion", true), looks);
+        assert_eq!(known_name(&progress, "Health Potion", false), "Health Potion");
+
+        assert_eq!(identify(&mut progress, "Health Potion"), Some(looks));
+        assert_eq!(identify(&mut progress, "Health Potion"), None);
+        assert_eq!(known_name(&progress, "Health Potion", true), "Health Potion");
+    }
+
+    #[test]
+    fn no_two_potions_share_a_colour() {
+        let mut progress = RunProgress::default();
+        let mut rng = RandomNumberGenerator::seeded(7);
+        for kind in 0 .. POTION_COLOURS.len() {
+            let appearance = new_appearance(&progress, &mut rng, MagicKind::Potion);
+            progress.appearances.insert(format!("Potion {}", kind), appearance);
+        }
+        let colours : Vec<RGB> = progress.appearances.values().map(|a| a.fg).collect();
+        for (i, colour) in colours.iter().enumerate() {
+            assert!(!colours[i + 1 ..].contains(colour));
+        }
+    }
+}
@@ -1,23 +1,36 @@
 use specs::prelude::*;
 use super::{
+    aoe_tiles,
     AreaOfEffect,
-    CombatStats,
-    Confusion,
-    Consumable,
+    Attributes,
+    Cursed,
+    EffectQueue,
+    EffectType,
+    Encumbrance,
+    EquipmentSlot,
     Equippable,
     Equipped,
     gamelog::GameLog,
+    GoalItem,
+    Gold,
+    identification::known_name,
     InBackpack,
-    InflictsDamage,
+    MagicItem,
     Map,
+    max_carry_lbs,
     Name,
     Position,
-    ProvidesHealing,
-    SufferDamage,
+    progress::RunProgress,
+    Purse,
+    spawner,
+    Stackable,
+    Targets,
+    TwoHanded,
     WantsToUseItem,
     WantsToDropItem,
     WantsToPickupItem,
-    WantsToRemoveItem
+    WantsToRemoveItem,
+    Weight
 };
 
 pub struct ItemCollectionSystem {}
@@ -26,29 +39,100 @@ impl<'a> System<'a> for ItemCollectionSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (ReadExpect<'a, Entity>,
                        WriteExpect<'a, GameLog>,
+                       Entities<'a>,
                        WriteStorage<'a, WantsToPickupItem>,
                        WriteStorage<'a, Position>,
                        ReadStorage<'a, Name>,
-                       WriteStorage<'a, InBackpack>);
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, GoalItem>,
+                       ReadStorage<'a, MagicItem>,
+                       WriteStorage<'a, Stackable>,
+                       ReadExpect<'a, RunProgress>,
+                       ReadStorage<'a, Attributes>,
+                       ReadStorage<'a, Weight>,
+                       WriteStorage<'a, Encumbrance>,
+                       ReadStorage<'a, Gold>,
+                       WriteStorage<'a, Purse>);
 
     fn run(&mut self, data : Self::SystemData) {
         let (
             player_entity,
             mut gamelog,
+            entities,
             mut wants_pickup,
             mut positions,
             names,
-            mut backpack
+            mut backpack,
+            goals,
+            magic,
+            mut stacks,
+            progress,
+            attributes,
+            weights,
+            mut encumbrance,
+            gold,
+            mut purses
         ) = data;
 
         for pickup in wants_pickup.join() {
+            // Coins go straight into the purse of anyone who has one
+            if let (Some(coins), Some(purse)) = (gold.get(pickup.item), purses.get_mut(pickup.collected_by)) {
+                purse.gold += coins.amount;
+                entities.delete(pickup.item).expect("Unable to delete gold");
+                if pickup.collected_by == *player_entity {
+                    gamelog.entries.push(format!("You pick up {} gold.", coins.amount));
+                }
+                continue;
+            }
+
+            let item_name = &names.get(pickup.item).unwrap().name;
+            let quantity = stacks.get(pickup.item).map(|stack| stack.quantity);
+
+            // Nothing more goes in the pack once it would be too heavy to move with
+            let item_lbs = weights.get(pickup.item).map_or(0.0, |weight| weight.lbs) * quantity.unwrap_or(1) as f32;
+            if let (Some(attr), Some(burden)) = (attributes.get(pickup.collected_by), encumbrance.get_mut(pickup.collected_by)) {
+                if burden.carried_lbs + item_lbs > max_carry_lbs(attr.might.total()) {
+                    if pickup.collected_by == *player_entity {
+                        let name = known_name(&progress, item_name, magic.get(pickup.item).is_some());
+                        gamelog.entries.push(format!("You can't carry the {} as well, it's too heavy.", name));
+                    }
+                    continue;
+                }
+                burden.carried_lbs += item_lbs;
+            }
+
+            // Stackable items join a matching stack that is already carried
+            let existing = match quantity {
+                None => None,
+                Some(_) => (&entities, &backpack, &names, &stacks).join()
+                    .find(|(entity, pack, name, _stack)| {
+                        *entity != pickup.item && pack.owner == pickup.collected_by && name.name == *item_name
+                    })
+                    .map(|(entity, _pack, _name, _stack)| entity)
+            };
+
             positions.remove(pickup.item);
-            backpack.insert(pickup.item,
-                            InBackpack{ owner : pickup.collected_by })
-                .expect("Unable to insert backpack entry");
+            match (existing, quantity) {
+                (Some(existing), Some(quantity)) => {
+                    stacks.get_mut(existing).unwrap().quantity += quantity;
+                    entities.delete(pickup.item).expect("Unable to delete merged item");
+                }
+                _ => {
+                    backpack.insert(pickup.item,
+                                    InBackpack{ owner : pickup.collected_by })
+                        .expect("Unable to insert backpack entry");
+                }
+            }
 
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!("You pick up the {}.", names.get(pickup.item).unwrap().name));
+                let name = known_name(&progress, item_name, magic.get(pickup.item).is_some());
+                match quantity {
+                    Some(quantity) if quantity > 1 => gamelog.entries.push(format!("You pick up the {} (x{}).", name, quantity)),
+                    _ => gamelog.entries.push(format!("You pick up the {}.", name))
+                }
+                if goals.get(pickup.item).is_some() {
+                    gamelog.entries.push("Now carry it back up to the surface!".to_string());
+                }
             }
         }
 
@@ -56,203 +140,140 @@ impl<'a> System<'a> for ItemCollectionSystem {
     }
 }
 
-pub struct ItemUseSystem {}
+pub struct ItemEquipOnUse {}
 
-impl<'a> System<'a> for ItemUseSystem {
+impl<'a> System<'a> for ItemEquipOnUse {
     #[allow(clippy::type_complexity)]
-    type SystemData = (WriteExpect<'a, Map>,
-                       ReadExpect<'a, Entity>,
+    type SystemData = (ReadExpect<'a, Entity>,
                        WriteExpect<'a, GameLog>,
                        Entities<'a>,
                        WriteStorage<'a, WantsToUseItem>,
                        ReadStorage<'a, Name>,
-                       ReadStorage<'a, Consumable>,
-                       ReadStorage<'a, ProvidesHealing>,
-                       ReadStorage<'a, InflictsDamage>,
-                       WriteStorage<'a, Confusion>,
-                       WriteStorage<'a, CombatStats>,
-                       WriteStorage<'a, SufferDamage>,
-                       WriteStorage<'a, AreaOfEffect>,
                        ReadStorage<'a, Equippable>,
                        WriteStorage<'a, Equipped>,
-                       WriteStorage<'a, InBackpack>);
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, TwoHanded>,
+                       ReadStorage<'a, Cursed>);
 
     fn run(&mut self, data : Self::SystemData) {
         let (
-            map,
             player_entity,
             mut gamelog,
             entities,
             mut wants_use,
             names,
-            consumables,
-            healing,
-            damaging,
-            mut confused,
-            mut combat_stats,
-            mut suffer_damage,
-            aoe,
             equippable,
             mut equipped,
-            mut backpack
+            mut backpack,
+            two_handed,
+            cursed
         ) = data;
 
-        for (entity, useitem) in (&entities, &wants_use).join() {
-            let mut used_item = true;
-
-            // Targeting
-            let mut targets : Vec<Entity> = Vec::new();
-            match useitem.target {
-                None => {
-                    // No target provided by WantsToUseItem, so target self
-                    targets.push(*player_entity);
-                }
-                Some(target) => {
-                    // Target Point provided
-                    let area_effect = aoe.get(useitem.item);
-                    match area_effect {
-                        None => {
-                            // Single target in tile
-                            let idx = map.xy_idx(target.x, target.y);
-                            for mob in map.tile_content[idx].iter() {
-                                targets.push(*mob);
-                            }
-                        }
-                        Some(area_effect) => {
-                            // Area of effect
-                            let mut blast_tiles = rltk::field_of_view(target, area_effect.radius, &*map);
-                            blast_tiles.retain(|p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1);
-                            for tile in blast_tiles.iter() {
-                                let idx = map.xy_idx(tile.x, tile.y);
-                                for mob in map.tile_content[idx].iter() {
-                                    targets.push(*mob);
-                                }
-                            }
-                        }
-                    }
+        let mut remove_use : Vec<Entity> = Vec::new();
+        for (target, useitem) in (&entities, &wants_use).join() {
+            // If item is equippable, unequip whatever is in the same slot, and equip this one
+            let can_equip = match equippable.get(useitem.item) {
+                None => continue,
+                Some(can_equip) => can_equip
+            };
+            let mut target_slot = can_equip.slot;
+
+            // Rings go on whichever hand is free
+            let slot_taken = |slot : EquipmentSlot| {
+                (&equipped).join().any(|e| e.owner == target && e.slot == slot)
+            };
+            if target_slot == EquipmentSlot::LeftRing &&
+                slot_taken(EquipmentSlot::LeftRing) && !slot_taken(EquipmentSlot::RightRing) {
+                target_slot = EquipmentSlot::RightRing;
+            } else if target_slot == EquipmentSlot::RightRing &&
+                slot_taken(EquipmentSlot::RightRing) && !slot_taken(EquipmentSlot::LeftRing) {
+                target_slot = EquipmentSlot::LeftRing;
+            }
+            let needs_both_hands = two_handed.get(useitem.item).is_some();
+
+            // Remove any items the target has in the item's slot, as well as
+            // anything in the other hand if either item needs both hands
+            let mut to_unequip : Vec<Entity> = Vec::new();
+            for (item_entity, already_equipped) in (&entities, &equipped).join() {
+                if already_equipped.owner != target { continue; }
+                let same_slot = already_equipped.slot == target_slot;
+                let frees_off_hand = needs_both_hands && already_equipped.slot == EquipmentSlot::Shield;
+                let blocks_off_hand = target_slot == EquipmentSlot::Shield &&
+                    two_handed.get(item_entity).is_some();
+                if same_slot || frees_off_hand || blocks_off_hand {
+                    to_unequip.push(item_entity);
                 }
             }
 
-
-            // If item heals, apply the healing
-            let item_heals = healing.get(useitem.item);
-            match item_heals {
-                None => {}
-                Some(healer) => {
-                    for target in targets.iter() {
-                        let stats = combat_stats.get_mut(*target);
-                        if let Some(stats) = stats {
-                            stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
-                            if entity == *player_entity {
-                                gamelog.entries.push(format!("You drink the {}, healing {} hp.",
-                                                             names.get(useitem.item).unwrap().name,
-                                                             healer.heal_amount));
-                            }
-                        }
-                    }
+            // A cursed item in the way blocks the whole change
+            remove_use.push(target);
+            if let Some(stuck) = to_unequip.iter().find(|item| cursed.get(**item).is_some()) {
+                if target == *player_entity {
+                    gamelog.entries.push(format!("You can't remove the cursed {}!", names.get(*stuck).unwrap().name));
                 }
+                continue;
             }
 
-            // If it inflicts damage, apply it to the target cell
-            let item_damages = damaging.get(useitem.item);
-            match item_damages {
-                None => {}
-                Some(damage) => {
-                    used_item = false;
-                    for mob in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
-                        if entity == *player_entity {
-                            let mob_name = names.get(*mob).unwrap();
-                            let item_name = names.get(useitem.item).unwrap();
-                            gamelog.entries.push(
-                                format!(
-                                    "You use {} on {}, inflicting {} hp.",
-                                    item_name.name,
-                                    mob_name.name,
-                                    damage.damage
-                                )
-                            );
-                        }
-                        used_item = true;
-                    }
+            for item in to_unequip.iter() {
+                if target == *player_entity {
+                    gamelog.entries.push(format!("You unequip {}.", names.get(*item).unwrap().name));
                 }
+                equipped.remove(*item);
+                backpack.insert(*item, InBackpack{ owner : target })
+                    .expect("Unable to insert backpack entry");
             }
 
-            let mut add_confusion = Vec::new();
-            {
-                let causes_confusion = confused.get(useitem.item);
-                match causes_confusion {
-                    None => {}
-                    Some(confusion) => {
-                        used_item = false;
-                        for mob in targets.iter() {
-                            add_confusion.push((*mob, confusion.turns));
-                            if entity == *player_entity {
-                                let mob_name = names.get(*mob).unwrap();
-                                let item_name = names.get(useitem.item).unwrap();
-                                gamelog.entries.push(
-                                    format!(
-                                        "You use {} on {}, confusing them.",
-                                        item_name.name,
-                                        mob_name.name
-                                    )
-                                );
-                            }
-                            used_item = true;
-                        }
-                    }
+            // Wield the item
+            equipped.insert(useitem.item, Equipped{ owner : target, slot : target_slot })
+                .expect("Unable to insert equipped component");
+            backpack.remove(useitem.item);
+            if target == *player_entity {
+                gamelog.entries.push(format!("You equip {}.", names.get(useitem.item).unwrap().name));
+                if cursed.get(useitem.item).is_some() {
+                    gamelog.entries.push("It is cursed, and will not come off!".to_string());
                 }
             }
+        }
 
-            for mob in add_confusion.iter() {
-                confused.insert(mob.0, Confusion{ turns : mob.1 }).expect("Unable to insert status"); 
-            }
+        for entity in remove_use.iter() {
+            wants_use.remove(*entity);
+        }
+    }
+}
 
-            // If item is equippable, unequip whatever is in the same slot, and equip this one
-            let item_equippable = equippable.get(useitem.item);
-            match item_equippable {
-                None => {}
-                Some(can_equip) => {
-                    let target_slot = can_equip.slot;
-                    let target = targets[0];
-
-                    // Remove any items the target has in the item's slot
-                    let mut to_unequip : Vec<Entity> = Vec::new();
-                    for (item_entity, already_equipped, name) in (&entities, &equipped, &names).join() {
-                        if already_equipped.owner == target && already_equipped.slot == target_slot {
-                            to_unequip.push(item_entity);
-                            if target == *player_entity {
-                                gamelog.entries.push(format!("You unequip {}.", name.name));
-                            }
-                        }
-                    }
+pub struct ItemUseSystem {}
 
-                    for item in to_unequip.iter() {
-                        equipped.remove(*item);
-                        backpack.insert(*item, InBackpack{ owner : target })
-                            .expect("Unable to insert backpack entry");
-                    }
+impl<'a> System<'a> for ItemUseSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, Map>,
+                       Entities<'a>,
+                       WriteStorage<'a, WantsToUseItem>,
+                       ReadStorage<'a, AreaOfEffect>,
+                       WriteExpect<'a, EffectQueue>);
 
-                    // Wield the item
-                    equipped.insert(useitem.item, Equipped{ owner : target, slot : target_slot })
-                        .expect("Unable to insert equipped component");
-                    backpack.remove(useitem.item);
-                    if target == *player_entity {
-                        gamelog.entries.push(format!("You equip {}.", names.get(useitem.item).unwrap().name));
-                    }
-                }
-            }
+    fn run(&mut self, data : Self::SystemData) {
+        let (
+            map,
+            entities,
+            mut wants_use,
+            aoe,
+            mut effects
+        ) = data;
 
-            // Remove consumable after its use
-            if used_item {
-                let consumable = consumables.get(useitem.item);
-                match consumable {
-                    None => {}
-                    Some(_) => {
-                        entities.delete(useitem.item).expect("Delete failed");
+        // The item's components decide what actually happens; that is
+        // resolved by the effects queue
+        for (entity, useitem) in (&entities, &wants_use).join() {
+            let targets = match useitem.target {
+                // No target provided by WantsToUseItem, so target self
+                None => Targets::Single{ target : entity },
+                Some(target) => {
+                    match aoe.get(useitem.item) {
+                        None => Targets::Tile{ tile_idx : map.xy_idx(target.x, target.y) as i32 },
+                        Some(area_effect) => Targets::Tiles{ tiles : aoe_tiles(&map, target, area_effect.radius) }
                     }
                 }
-            }
+            };
+            effects.add(Some(entity), EffectType::ItemUse{ item : useitem.item }, targets);
         }
 
         wants_use.clear();
@@ -269,7 +290,10 @@ impl<'a> System<'a> for ItemDropSystem {
                        WriteStorage<'a, WantsToDropItem>,
                        ReadStorage<'a, Name>,
                        WriteStorage<'a, Position>,
-                       WriteStorage<'a, InBackpack>);
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, MagicItem>,
+                       ReadStorage<'a, Stackable>,
+                       ReadExpect<'a, RunProgress>);
 
     fn run(&mut self, data : Self::SystemData) {
         let (
@@ -279,7 +303,10 @@ impl<'a> System<'a> for ItemDropSystem {
             mut wants_drop,
             names,
             mut positions,
-            mut backpack
+            mut backpack,
+            magic,
+            stacks,
+            progress
         ) = data;
 
         for (entity, to_drop) in (&entities, &wants_drop).join() {
@@ -289,8 +316,11 @@ impl<'a> System<'a> for ItemDropSystem {
             backpack.remove(to_drop.item);
 
             if entity == *player_entity {
-                gamelog.entries.push(format!("You drop the {}.",
-                                             names.get(to_drop.item).unwrap().name));
+                let name = known_name(&progress, &names.get(to_drop.item).unwrap().name, magic.get(to_drop.item).is_some());
+                match stacks.get(to_drop.item) {
+                    Some(stack) if stack.quantity > 1 => gamelog.entries.push(format!("You drop the {} (x{}).", name, stack.quantity)),
+                    _ => gamelog.entries.push(format!("You drop the {}.", name))
+                }
             }
         }
         wants_drop.clear();
@@ -302,19 +332,33 @@ pub struct ItemRemoveSystem {}
 impl<'a> System<'a> for ItemRemoveSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
                        WriteStorage<'a, WantsToRemoveItem>,
                        WriteStorage<'a, Equipped>,
-                       WriteStorage<'a, InBackpack>);
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, Cursed>,
+                       ReadStorage<'a, Name>);
 
     fn run(&mut self, data : Self::SystemData) {
         let (
             entities,
+            player_entity,
+            mut gamelog,
             mut wants_remove,
             mut equipped,
-            mut backpack
+            mut backpack,
+            cursed,
+            names
         ) = data;
 
         for (entity, to_remove) in (&entities, &wants_remove).join() {
+            if cursed.get(to_remove.item).is_some() {
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You can't remove the cursed {}!", names.get(to_remove.item).unwrap().name));
+                }
+                continue;
+            }
             equipped.remove(to_remove.item);
             backpack.insert(to_remove.item, InBackpack{ owner : entity })
                 .expect("Unable to insert backpack");
@@ -323,3 +367,132 @@ impl<'a> System<'a> for ItemRemoveSystem {
         wants_remove.clear();
     }
 }
+
+/// Take `quantity` items off a carried stack and make them a stack of their
+/// own in the same backpack. The new stack is spawned afresh by name, so it
+/// has everything the item was built with. Returns the new stack.
+pub fn split_stack(ecs : &mut World, item : Entity, quantity : i32) -> Entity {
+    let name = ecs.read_storage::<Name>().get(item).map(|n| n.name.clone()).unwrap_or_default();
+    let new_stack = spawner::spawn_entity(ecs, &name, 0, 0).expect("Unable to spawn split stack");
+    ecs.write_storage::<Position>().remove(new_stack);
+    let pack = ecs.read_storage::<InBackpack>().get(item).cloned();
+    if let Some(pack) = pack {
+        ecs.write_storage::<InBackpack>().insert(new_stack, pack).expect("Unable to insert backpack entry");
+    }
+
+    let mut stacks = ecs.write_storage::<Stackable>();
+    stacks.insert(new_stack, Stackable{ quantity }).expect("Unable to insert stack");
+    if let Some(stack) = stacks.get_mut(item) {
+        stack.quantity -= quantity;
+    }
+    new_stack
+}
+
+/// Put an item in an owner's pack, joining a matching stack if they already carry one
+pub fn give_item(ecs : &mut World, item : Entity, owner : Entity) {
+    ecs.write_storage::<Position>().remove(item);
+
+    let existing = {
+        let entities = ecs.entities();
+        let names = ecs.read_storage::<Name>();
+        let backpack = ecs.read_storage::<InBackpack>();
+        let stacks = ecs.read_storage::<Stackable>();
+        match (names.get(item), stacks.get(item)) {
+            (Some(item_name), Some(_stack)) => (&entities, &backpack, &names, &stacks).join()
+                .find(|(entity, pack, name, _stack)| *entity != item && pack.owner == owner && name.name == item_name.name)
+                .map(|(entity, _pack, _name, _stack)| entity),
+            _ => None
+        }
+    };
+
+    match existing {
+        Some(existing) => {
+            {
+                let mut stacks = ecs.write_storage::<Stackable>();
+                let quantity = stacks.get(item).unwrap().quantity;
+                stacks.get_mut(existing).unwrap().quantity += quantity;
+            }
+            ecs.delete_entity(item).expect("Unable to delete merged item");
+        }
+        None => {
+            ecs.write_storage::<InBackpack>().insert(item, InBackpack{ owner })
+                .expect("Unable to insert backpack entry");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ Item, MagicItem, ProvidesHealing, Renderable, test_world, Value };
+
+    fn potions(ecs : &mut World, owner : Entity, quantity : i32) -> Entity {
+        let potions = spawner::spawn_entity(ecs, "Health Potion", 0, 0).unwrap();
+        ecs.write_storage::<Stackable>().insert(potions, Stackable{ quantity }).unwrap();
+        give_item(ecs, potions, owner);
+        potions
+    }
+
+    fn quantity(ecs : &World, item : Entity) -> i32 {
+        ecs.read_storage::<Stackable>().get(item).unwrap().quantity
+    }
+
+    #[test]
+    fn splitting_a_stack_copies_the_item() {
+        let mut ecs = test_world();
+        let owner = ecs.create_entity().build();
+        let stack = potions(&mut ecs, owner, 5);
+
+        let split = split_stack(&mut ecs, stack, 2);
+        assert_ne!(split, stack);
+        assert_eq!(quantity(&ecs, stack), 3);
+        assert_eq!(quantity(&ecs, split), 2);
+        assert_eq!(ecs.read_storage::<InBackpack>().get(split).unwrap().owner, owner);
+        assert_eq!(ecs.read_storage::<Name>().get(split).unwrap().name, "Health Potion");
+        assert_eq!(ecs.read_storage::<Value>().get(split).unwrap().gold, ecs.read_storage::<Value>().get(stack).unwrap().gold);
+        assert_eq!(ecs.read_storage::<Weight>().get(split).unwrap().lbs, 0.5);
+        assert!(ecs.read_storage::<ProvidesHealing>().get(split).is_some());
+        assert!(ecs.read_storage::<Position>().get(split).is_none());
+    }
+
+    #[test]
+    fn a_split_stack_keeps_its_disguise() {
+        let mut ecs = test_world();
+        let owner = ecs.create_entity().build();
+        let stack = potions(&mut ecs, owner, 2);
+
+        let split = split_stack(&mut ecs, stack, 1);
+        assert!(ecs.read_storage::<MagicItem>().get(split).is_some());
+        let renderables = ecs.read_storage::<Renderable>();
+        assert_eq!(renderables.get(split).unwrap().fg, renderables.get(stack).unwrap().fg);
+    }
+
+    #[test]
+    fn giving_an_item_joins_a_matching_stack() {
+        let mut ecs = test_world();
+        let giver = ecs.create_entity().build();
+        let taker = ecs.create_entity().build();
+        let given = potions(&mut ecs, giver, 2);
+        let kept = potions(&mut ecs, taker, 3);
+
+        give_item(&mut ecs, given, taker);
+        ecs.maintain();
+        assert_eq!(quantity(&ecs, kept), 5);
+        assert!(!ecs.is_alive(given));
+    }
+
+    #[test]
+    fn giving_an_unstackable_item_just_moves_it() {
+        let mut ecs = test_world();
+        let taker = ecs.create_entity().build();
+        let dagger = ecs.create_entity()
+            .with(Name{ name : "Dagger".to_string() })
+            .with(Item{})
+            .with(Position{ x : 3, y : 4 })
+            .build();
+
+        give_item(&mut ecs, dagger, taker);
+        assert!(ecs.read_storage::<Position>().get(dagger).is_none());
+        assert_eq!(ecs.read_storage::<InBackpack>().get(dagger).unwrap().owner, taker);
+    }
+}
@@ -26,12 +26,42 @@ use damage_system::*;
 mod gui;
 mod gamelog;
 use gamelog::GameLog;
+mod gameclock;
+use gameclock::GameClock;
+mod progress;
+use progress::RunProgress;
+mod arena;
+mod identification;
 mod spawner;
+mod trade;
 mod inventory_system;
 use inventory_system::*;
 mod saveload_system;
 mod random_table;
 use random_table::RandomTable;
+mod ranged_combat_system;
+use ranged_combat_system::RangedCombatSystem;
+mod status_effect_system;
+use status_effect_system::StatusEffectSystem;
+mod hunger_system;
+use hunger_system::HungerSystem;
+mod regen_system;
+use regen_system::RegenSystem;
+mod encumbrance_system;
+use encumbrance_system::EncumbranceSystem;
+mod gamesystem;
+pub use gamesystem::*;
+mod effects;
+pub use effects::*;
+mod particle_system;
+mod pathing;
+mod movement_system;
+mod monster_ability_system;
+use monster_ability_system::MonsterAbilitySystem;
+use movement_system::MovementSystem;
+mod trigger_system;
+use trigger_system::{ trap_targets, TriggerSystem };
+use pathing::{ PathingMaps, PathingSystem };
 
 // Consts
 const SHOW_FPS : bool = false;
@@ -44,12 +74,21 @@ pub enum RunState {
     MonsterTurn,
     ShowInventory,
     ShowDropItem,
+    ShowDropQuantity { item : Entity, quantity : i32 },
+    ShowVendor { vendor : Entity, mode : gui::VendorMode },
+    ShowContainer { container : Entity },
     ShowTargeting { range : i32, item : Entity },
+    ShowFireTarget { target : Entity },
     MainMenu { menu_selection : gui::MainMenuSelection },
     SaveGame,
     NextLevel,
+    PreviousLevel,
     ShowRemoveItem,
-    GameOver
+    ShowCharacterSheet,
+    ShowEquipment,
+    LevelUp,
+    GameOver,
+    Victory
 }
 
 // Struct State - a class
@@ -61,22 +100,43 @@ impl State {
     fn run_systems(&mut self) {
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
+        let mut pathing = PathingSystem{};
+        pathing.run_now(&self.ecs);
         let mut mob = MonsterAI {};
         mob.run_now(&self.ecs);
+        let mut abilities = MonsterAbilitySystem{};
+        abilities.run_now(&self.ecs);
+        let mut movement = MovementSystem{};
+        movement.run_now(&self.ecs);
         let mut map_index = MapIndexingSystem{};
         map_index.run_now(&self.ecs);
+        let mut triggers = TriggerSystem{};
+        triggers.run_now(&self.ecs);
         let mut melee_combat = MeleeCombatSystem{};
         melee_combat.run_now(&self.ecs);
-        let mut damage = DamageSystem{};
-        damage.run_now(&self.ecs);
+        let mut ranged_combat = RangedCombatSystem{};
+        ranged_combat.run_now(&self.ecs);
+        let mut status_effects = StatusEffectSystem{};
+        status_effects.run_now(&self.ecs);
+        let mut hunger = HungerSystem{};
+        hunger.run_now(&self.ecs);
+        let mut regen = RegenSystem{};
+        regen.run_now(&self.ecs);
         let mut pickup = ItemCollectionSystem{};
         pickup.run_now(&self.ecs);
+        let mut equip_items = ItemEquipOnUse{};
+        equip_items.run_now(&self.ecs);
         let mut item_use = ItemUseSystem{};
         item_use.run_now(&self.ecs);
         let mut drop_items = ItemDropSystem{};
         drop_items.run_now(&self.ecs);
         let mut remove_items = ItemRemoveSystem{};
         remove_items.run_now(&self.ecs);
+        let mut encumbrance = EncumbranceSystem{};
+        encumbrance.run_now(&self.ecs);
+        effects::run_effects_queue(&mut self.ecs);
+        let mut damage = DamageSystem{};
+        damage.run_now(&self.ecs);
 
         self.ecs.maintain();
     }
@@ -124,7 +184,9 @@ impl State {
         return to_delete;
     }
 
-    fn goto_next_level(&mut self) {
+    /// Move the player up (-1) or down (+1) one level. Levels are built fresh
+    /// each time; the player arrives on the stairs leading back the other way.
+    fn goto_level(&mut self, offset : i32) {
         // Delete entities that aren't the player or his equipment
         let to_delete = self.entities_to_remove_on_level_change();
         for target in to_delete {
@@ -138,17 +200,19 @@ impl State {
         {
             let mut worldmap_resource = self.ecs.write_resource::<Map>();
             current_depth = worldmap_resource.depth;
-            *worldmap_resource = Map::new_map_rooms_and_corridors(current_depth + 1);
+            *worldmap_resource = Map::new_level(current_depth + offset);
             worldmap = worldmap_resource.clone();
         }
 
         // Spawn mobs
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, current_depth + 1);
-        }
+        spawner::spawn_level(&mut self.ecs, &worldmap);
 
         // Place the player and update resources
-        let (player_x, player_y) = worldmap.rooms[0].center();
+        let (player_x, player_y) = if offset < 0 {
+            worldmap.down_stairs().unwrap_or_else(|| worldmap.rooms[0].center())
+        } else {
+            worldmap.rooms[0].center()
+        };
         let mut player_position = self.ecs.write_resource::<Point>();
         *player_position = Point::new(player_x, player_y);
         let mut position_components = self.ecs.write_storage::<Position>();
@@ -167,13 +231,12 @@ impl State {
             vs.dirty = true;
         }
 
-        // Notify the player and give them some health
+        // Notify the player
         let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
-        gamelog.entries.push("You descend to the next level, and take a moment to rest.".to_string());
-        let mut player_health_store = self.ecs.write_storage::<CombatStats>();
-        let player_health = player_health_store.get_mut(*player_entity);
-        if let Some(player_health) = player_health {
-            player_health.hp = i32::max(player_health.hp, player_health.max_hp / 2);
+        if offset < 0 {
+            gamelog.entries.push("You climb back towards the surface.".to_string());
+        } else {
+            gamelog.entries.push("You descend to the next level.".to_string());
         }
     }
 
@@ -186,20 +249,20 @@ impl State {
         for del in to_delete.iter() {
             self.ecs.delete_entity(*del).expect("Deletion failed");
         }
+        *self.ecs.write_resource::<GameClock>() = GameClock::default();
+        *self.ecs.write_resource::<RunProgress>() = RunProgress::default();
 
         // Build a new map and place the player
         let worldmap;
 
         {
             let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            *worldmap_resource = Map::new_map_rooms_and_corridors(1);
+            *worldmap_resource = Map::new_level(1);
             worldmap = worldmap_resource.clone();
         }
 
         // Spawn mobs
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, 1);
-        }
+        spawner::spawn_level(&mut self.ecs, &worldmap);
 
         // Place the player and update resources
         let (player_x, player_y) = worldmap.rooms[0].center();
@@ -229,6 +292,7 @@ impl State {
 impl GameState for State {
     fn tick(&mut self, ctx: &mut Rltk) {
         ctx.cls();
+        particle_system::cull_dead_particles(&mut self.ecs, ctx);
 
         let mut newrunstate;
         {
@@ -240,15 +304,19 @@ impl GameState for State {
         match newrunstate {
             RunState::MainMenu{..} => {}
             RunState::GameOver{..} => {}
+            RunState::Victory => {}
             _ => {
                 draw_map(&self.ecs, ctx);
 
                 {
                     let positions = self.ecs.read_storage::<Position>();
                     let renderables = self.ecs.read_storage::<Renderable>();
+                    let hidden = self.ecs.read_storage::<Hidden>();
                     let map = self.ecs.fetch::<Map>();
 
-                    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
+                    let mut data = (&positions, &renderables, !&hidden).join()
+                        .map(|(pos, render, _hidden)| (pos, render))
+                        .collect::<Vec<_>>();
                     data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
                     for (pos, render) in data.iter() {
                         let idx = map.xy_idx(pos.x, pos.y);
@@ -273,11 +341,37 @@ impl GameState for State {
             }
             RunState::PlayerTurn => {
                 self.run_systems();
-                newrunstate = RunState::MonsterTurn;
+                let player_turns = {
+                    let mut clock = self.ecs.write_resource::<GameClock>();
+                    clock.player_turns += 1;
+                    clock.player_turns
+                };
+
+                // A hasted player gets every other action for free
+                if player::player_has_status(&self.ecs, StatusKind::Haste) && player_turns % 2 == 0 {
+                    newrunstate = RunState::AwaitingInput;
+                } else {
+                    newrunstate = RunState::MonsterTurn;
+                }
             }
             RunState::MonsterTurn => {
                 self.run_systems();
-                newrunstate = RunState::AwaitingInput;
+                let turn = {
+                    let mut clock = self.ecs.write_resource::<GameClock>();
+                    clock.turn += 1;
+                    clock.turn
+                };
+
+                // A slowed or overloaded player lets the monsters go twice every other round
+                let slowed = player::player_has_status(&self.ecs, StatusKind::Slow) ||
+                    player::player_encumbrance(&self.ecs) == EncumbranceState::Overloaded;
+                if slowed && turn % 2 == 0 {
+                    newrunstate = RunState::MonsterTurn;
+                } else if player::can_level_up(&self.ecs) {
+                    newrunstate = RunState::LevelUp;
+                } else {
+                    newrunstate = RunState::AwaitingInput;
+                }
             }
             RunState::ShowInventory => {
                 let result = gui::show_inventory(self, ctx);
@@ -311,15 +405,74 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
+                        let held = self.ecs.read_storage::<Stackable>().get(item_entity).map(|stack| stack.quantity);
+                        match held {
+                            Some(quantity) if quantity > 1 => {
+                                newrunstate = RunState::ShowDropQuantity{ item : item_entity, quantity };
+                            }
+                            _ => {
+                                let mut intent = self.ecs.write_storage::<WantsToDropItem>();
+                                intent.insert(
+                                    *self.ecs.fetch::<Entity>(),
+                                    WantsToDropItem{ item : item_entity }
+                                ).expect("Unable to insert intent");
+                                newrunstate = RunState::PlayerTurn;
+                            }
+                        }
+                    }
+                }
+            }
+            RunState::ShowDropQuantity{ item, quantity } => {
+                let result = gui::drop_quantity_menu(self, ctx, item, quantity);
+                match result {
+                    gui::DropQuantityResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::DropQuantityResult::NoResponse => {}
+                    gui::DropQuantityResult::Adjust{ quantity } => {
+                        newrunstate = RunState::ShowDropQuantity{ item, quantity };
+                    }
+                    gui::DropQuantityResult::Drop{ quantity } => {
+                        let held = self.ecs.read_storage::<Stackable>().get(item).map_or(1, |stack| stack.quantity);
+                        let to_drop = if quantity < held {
+                            split_stack(&mut self.ecs, item, quantity)
+                        } else {
+                            item
+                        };
                         let mut intent = self.ecs.write_storage::<WantsToDropItem>();
                         intent.insert(
                             *self.ecs.fetch::<Entity>(),
-                            WantsToDropItem{ item : item_entity }
+                            WantsToDropItem{ item : to_drop }
                         ).expect("Unable to insert intent");
                         newrunstate = RunState::PlayerTurn;
                     }
                 }
             }
+            RunState::ShowVendor{ vendor, mode } => {
+                let result = gui::show_vendor(self, ctx, vendor, mode);
+                match result {
+                    gui::VendorResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::VendorResult::NoResponse => {}
+                    gui::VendorResult::SwitchMode{ mode } => {
+                        newrunstate = RunState::ShowVendor{ vendor, mode };
+                    }
+                    gui::VendorResult::Buy{ item } => trade::buy(&mut self.ecs, item),
+                    gui::VendorResult::Sell{ item } => trade::sell(&mut self.ecs, vendor, item)
+                }
+            }
+            RunState::ShowContainer{ container } => {
+                let result = gui::show_container(self, ctx, container);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let item_entity = result.1.unwrap();
+                        let player_entity = *self.ecs.fetch::<Entity>();
+                        let mut intent = self.ecs.write_storage::<WantsToPickupItem>();
+                        intent.insert(player_entity, WantsToPickupItem{ collected_by : player_entity, item : item_entity })
+                            .expect("Unable to insert intent");
+                        newrunstate = RunState::PlayerTurn;
+                    }
+                }
+            }
             RunState::ShowTargeting{range, item} => {
                 let result = gui::ranged_target(self, ctx, range);
                 match result.0 {
@@ -335,6 +488,22 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::ShowFireTarget{ target } => {
+                let result = gui::fire_target(self, ctx, target);
+                match result {
+                    gui::FireTargetResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::FireTargetResult::NoResponse => {}
+                    gui::FireTargetResult::Cycle{ target } => {
+                        newrunstate = RunState::ShowFireTarget{ target };
+                    }
+                    gui::FireTargetResult::Fire{ target } => {
+                        let mut intent = self.ecs.write_storage::<WantsToShoot>();
+                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToShoot{ target })
+                            .expect("Unable to insert intent");
+                        newrunstate = RunState::PlayerTurn;
+                    }
+                }
+            }
             RunState::MainMenu{..} => {
                 let result = gui::main_menu(self, ctx);
                 match result {
@@ -360,9 +529,17 @@ impl GameState for State {
                 newrunstate = RunState::MainMenu{ menu_selection : gui::MainMenuSelection::LoadGame }
             }
             RunState::NextLevel => {
-                self.goto_next_level();
+                self.goto_level(1);
                 newrunstate = RunState::PreRun;
             }
+            RunState::PreviousLevel => {
+                if self.ecs.fetch::<Map>().depth == 1 {
+                    newrunstate = RunState::Victory;
+                } else {
+                    self.goto_level(-1);
+                    newrunstate = RunState::PreRun;
+                }
+            }
             RunState::ShowRemoveItem => {
                 let result = gui::remove_item_menu(self, ctx);
                 match result.0 {
@@ -377,8 +554,51 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::ShowEquipment => {
+                let result = gui::show_equipment(self, ctx);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let item_entity = result.1.unwrap();
+                        let mut intent = self.ecs.write_storage::<WantsToRemoveItem>();
+                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToRemoveItem{ item : item_entity })
+                            .expect("Unable to insert intent");
+                        newrunstate = RunState::PlayerTurn;
+                    }
+                }
+            }
+            RunState::ShowCharacterSheet => {
+                let result = gui::show_character_sheet(self, ctx);
+                match result {
+                    gui::CharacterSheetResult::NoResponse => {}
+                    gui::CharacterSheetResult::Cancel => newrunstate = RunState::AwaitingInput
+                }
+            }
+            RunState::LevelUp => {
+                let result = gui::level_up_menu(self, ctx);
+                match result {
+                    gui::LevelUpResult::NoSelection => {}
+                    gui::LevelUpResult::Selected{ attribute } => {
+                        player::level_up(&mut self.ecs, attribute);
+                        if !player::can_level_up(&self.ecs) {
+                            newrunstate = RunState::AwaitingInput;
+                        }
+                    }
+                }
+            }
             RunState::GameOver => {
-                let result = gui::game_over(ctx);
+                let result = gui::game_over(&self.ecs, ctx);
+                match result {
+                    gui::GameOverResult::NoSelection => {}
+                    gui::GameOverResult::QuitToMenu => {
+                        self.game_over_cleanup();
+                        newrunstate = RunState::MainMenu{ menu_selection : gui::MainMenuSelection::NewGame };
+                    }
+                }
+            }
+            RunState::Victory => {
+                let result = gui::victory(&self.ecs, ctx);
                 match result {
                     gui::GameOverResult::NoSelection => {}
                     gui::GameOverResult::QuitToMenu => {
@@ -412,6 +632,94 @@ impl GameState for State {
     }
 }
 
+/// Every component the game uses, so a fresh World can hold any entity
+fn register_components(ecs : &mut World) {
+    ecs.register::<Position>();
+    ecs.register::<Renderable>();
+    ecs.register::<Player>();
+    ecs.register::<Viewshed>();
+    ecs.register::<Monster>();
+    ecs.register::<MonsterMind>();
+    ecs.register::<Faction>();
+    ecs.register::<Name>();
+    ecs.register::<BlocksTile>();
+    ecs.register::<CombatStats>();
+    ecs.register::<SufferDamage>();
+    ecs.register::<KilledBy>();
+    ecs.register::<WantsToMelee>();
+    ecs.register::<Item>();
+    ecs.register::<GoalItem>();
+    ecs.register::<MagicItem>();
+    ecs.register::<IdentifiesItems>();
+    ecs.register::<Cursed>();
+    ecs.register::<RemovesCurse>();
+    ecs.register::<ProvidesHealing>();
+    ecs.register::<ProvidesFood>();
+    ecs.register::<HungerClock>();
+    ecs.register::<Weight>();
+    ecs.register::<Encumbrance>();
+    ecs.register::<InBackpack>();
+    ecs.register::<WantsToPickupItem>();
+    ecs.register::<WantsToUseItem>();
+    ecs.register::<WantsToDropItem>();
+    ecs.register::<Consumable>();
+    ecs.register::<Stackable>();
+    ecs.register::<Value>();
+    ecs.register::<Gold>();
+    ecs.register::<Purse>();
+    ecs.register::<Vendor>();
+    ecs.register::<LootTable>();
+    ecs.register::<Container>();
+    ecs.register::<Locked>();
+    ecs.register::<Trapped>();
+    ecs.register::<Hidden>();
+    ecs.register::<EntryTrigger>();
+    ecs.register::<Trap>();
+    ecs.register::<RaisesAlarm>();
+    ecs.register::<EntityMoved>();
+    ecs.register::<Ranged>();
+    ecs.register::<InflictsDamage>();
+    ecs.register::<Resistances>();
+    ecs.register::<AreaOfEffect>();
+    ecs.register::<InflictsStatus>();
+    ecs.register::<StatusEffects>();
+    ecs.register::<Equippable>();
+    ecs.register::<Equipped>();
+    ecs.register::<MeleePowerBonus>();
+    ecs.register::<DefenseBonus>();
+    ecs.register::<WantsToRemoveItem>();
+    ecs.register::<Attributes>();
+    ecs.register::<Skills>();
+    ecs.register::<RangedWeapon>();
+    ecs.register::<Ammunition>();
+    ecs.register::<WantsToShoot>();
+    ecs.register::<WantsToMove>();
+    ecs.register::<TwoHanded>();
+    ecs.register::<Teleports>();
+    ecs.register::<Summons>();
+    ecs.register::<ConeOfEffect>();
+    ecs.register::<Ability>();
+    ecs.register::<ParticleLifetime>();
+
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
+}
+
+/// A world with everything registered and the resources that spawning and
+/// the item systems expect, for tests to build on
+#[cfg(test)]
+fn test_world() -> World {
+    let mut ecs = World::new();
+    register_components(&mut ecs);
+    ecs.insert(rltk::RandomNumberGenerator::seeded(1));
+    ecs.insert(GameLog { entries : Vec::new() });
+    ecs.insert(GameClock::default());
+    ecs.insert(RunProgress::default());
+    ecs.insert(EffectQueue::default());
+    ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+    ecs
+}
+
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
     let mut context = RltkBuilder::simple80x50()
@@ -424,42 +732,17 @@ fn main() -> rltk::BError {
         ecs : World::new()
     };
 
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Monster>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<CombatStats>();
-    gs.ecs.register::<SufferDamage>();
-    gs.ecs.register::<WantsToMelee>();
-    gs.ecs.register::<Item>();
-    gs.ecs.register::<ProvidesHealing>();
-    gs.ecs.register::<InBackpack>();
-    gs.ecs.register::<WantsToPickupItem>();
-    gs.ecs.register::<WantsToUseItem>();
-    gs.ecs.register::<WantsToDropItem>();
-    gs.ecs.register::<Consumable>();
-    gs.ecs.register::<Ranged>();
-    gs.ecs.register::<InflictsDamage>();
-    gs.ecs.register::<AreaOfEffect>();
-    gs.ecs.register::<Confusion>();
-    gs.ecs.register::<Equippable>();
-    gs.ecs.register::<Equipped>();
-    gs.ecs.register::<MeleePowerBonus>();
-    gs.ecs.register::<DefenseBonus>();
-    gs.ecs.register::<WantsToRemoveItem>();
-
-    gs.ecs.register::<SimpleMarker<SerializeMe>>();
-    gs.ecs.register::<SerializationHelper>();
+    register_components(&mut gs.ecs);
 
     gs.ecs.insert(rltk::RandomNumberGenerator::new());
     gs.ecs.insert(RunState::MainMenu{ menu_selection : gui::MainMenuSelection::NewGame });
     gs.ecs.insert(GameLog { entries : vec!["Hello".to_string()] });
+    gs.ecs.insert(GameClock::default());
+    gs.ecs.insert(RunProgress::default());
+    gs.ecs.insert(EffectQueue::default());
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
 
-    let map : Map = Map::new_map_rooms_and_corridors(1);
+    let map : Map = Map::new_level(1);
 
     let (player_x, player_y) = map.rooms[0].center();
 
@@ -485,10 +768,9 @@ fn main() -> rltk::BError {
 
     let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
 
-    for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gs.ecs, room, 1);
-    }
+    spawner::spawn_level(&mut gs.ecs, &map);
 
+    gs.ecs.insert(PathingMaps::new(&map));
     gs.ecs.insert(map);
     gs.ecs.insert(player_entity);
     gs.ecs.insert(Point::new(player_x, player_y));
@@ -11,7 +11,7 @@ use rltk::{
 use serde::{ Serialize, Deserialize };
 use specs::prelude::*;
 use std::cmp::{ max, min };
-use super::{ Rect };
+use super::{ arena, Rect };
 
 // Consts
 pub const MAPWIDTH : usize = 80;
@@ -28,13 +28,14 @@ pub const MAPCOUNT : usize = MAPHEIGHT * MAPWIDTH;
      Otherwise tile_type == TileType::Wall wouldn't compile */
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
-    Wall, Floor, DownStairs
+    Wall, Floor, DownStairs, UpStairs
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct Map {
     pub tiles : Vec<TileType>,
     pub rooms : Vec<Rect>,
+    pub shop_rooms : Vec<usize>,
     pub width : i32,
     pub height : i32,
     pub revealed_tiles : Vec<bool>,
@@ -96,6 +97,7 @@ impl Map {
         let mut map = Map{
             tiles : vec![TileType::Wall; MAPCOUNT],
             rooms : Vec::new(),
+            shop_rooms : Vec::new(),
             width : MAPWIDTH as i32,
             height : MAPHEIGHT as i32,
             revealed_tiles : vec![false; MAPCOUNT],
@@ -142,6 +144,7 @@ impl Map {
         let mut map = Map{
             tiles : vec![TileType::Wall; MAPCOUNT],
             rooms : Vec::new(),
+            shop_rooms : Vec::new(),
             width : MAPWIDTH as i32,
             height : MAPHEIGHT as i32,
             revealed_tiles : vec![false; MAPCOUNT],
@@ -226,9 +229,61 @@ impl Map {
         let stairs_idx = map.xy_idx(stairs_x, stairs_y);
         map.tiles[stairs_idx] = TileType::DownStairs;
 
+        // The way back up is where the player arrives
+        let (up_x, up_y) = map.rooms[0].center();
+        let up_idx = map.xy_idx(up_x, up_y);
+        map.tiles[up_idx] = TileType::UpStairs;
+
+        // Below the first level a room between the stairs is sometimes a shop
+        if new_depth > 1 && map.rooms.len() > 2 && rng.roll_dice(1, 3) == 1 {
+            let shop = rng.range(1, map.rooms.len() as i32 - 1) as usize;
+            map.shop_rooms.push(shop);
+        }
+
         map
     }
 
+    /// Hand-designed boss level; anything outside the layout is solid rock
+    pub fn new_arena(new_depth : i32, arena : &arena::Arena) -> Map {
+        let mut map = Map{
+            tiles : vec![TileType::Wall; MAPCOUNT],
+            rooms : Vec::new(),
+            shop_rooms : Vec::new(),
+            width : MAPWIDTH as i32,
+            height : MAPHEIGHT as i32,
+            revealed_tiles : vec![false; MAPCOUNT],
+            visible_tiles : vec![false; MAPCOUNT],
+            blocked : vec![false; MAPCOUNT],
+            tile_content : vec![Vec::new(); MAPCOUNT],
+            depth : new_depth
+        };
+
+        for (x, y, cell) in arena.cells(map.width, map.height) {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = match cell {
+                '#' => TileType::Wall,
+                '>' => TileType::DownStairs,
+                '<' => TileType::UpStairs,
+                _ => TileType::Floor
+            };
+
+            // The player arrives on the up stairs, in the centre of the first room
+            if cell == '<' {
+                map.rooms.push(Rect::new(x - 1, y - 1, 2, 2));
+            }
+        }
+
+        map
+    }
+
+    /// Milestone depths get their boss arena, everything else is random
+    pub fn new_level(new_depth : i32) -> Map {
+        match arena::arena_at(new_depth) {
+            Some(arena) => Map::new_arena(new_depth, arena),
+            None => Map::new_map_rooms_and_corridors(new_depth)
+        }
+    }
+
     fn is_exit_valid(&self, x : i32, y : i32) -> bool {
         if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
             return false;
@@ -244,6 +299,13 @@ impl Map {
         }
     }
 
+    /// Where the way down is, for players arriving from below
+    pub fn down_stairs(&self) -> Option<(i32, i32)> {
+        self.tiles.iter()
+            .position(|tile| *tile == TileType::DownStairs)
+            .map(|idx| (idx as i32 % self.width, idx as i32 / self.width))
+    }
+
     pub fn clear_content_index(&mut self) {
         for content in self.tile_content.iter_mut() {
             content.clear();
@@ -317,6 +379,10 @@ pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
                     glyph = rltk::to_cp437('>');
                     fg = RGB::from_f32(0.,1.0, 1.0);
                 }
+                TileType::UpStairs => {
+                    glyph = rltk::to_cp437('<');
+                    fg = RGB::from_f32(0.,1.0, 1.0);
+                }
             }
 
             if !map.visible_tiles[idx] { fg = fg.to_greyscale() }
@@ -1,27 +1,45 @@
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 use super::{
+    armor_class,
+    Attributes,
     CombatStats,
+    damage_bonus,
+    DamageType,
     DefenseBonus,
+    EffectQueue,
+    EffectType,
+    Encumbrance,
     Equipped,
     GameLog,
     MeleePowerBonus,
     Name,
-    SufferDamage,
+    practise_skill,
+    Skill,
+    skill_check,
+    Skills,
+    Targets,
     WantsToMelee
 };
 
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (Entities<'a>,
                        WriteStorage<'a, WantsToMelee>,
                        ReadStorage<'a, Name>,
                        ReadStorage<'a, CombatStats>,
-                       WriteStorage<'a, SufferDamage>,
+                       WriteExpect<'a, EffectQueue>,
                        WriteExpect<'a, GameLog>,
                        ReadStorage<'a, MeleePowerBonus>,
                        ReadStorage<'a, DefenseBonus>,
-                       ReadStorage<'a, Equipped>);
+                       ReadStorage<'a, Equipped>,
+                       ReadStorage<'a, Attributes>,
+                       WriteExpect<'a, RandomNumberGenerator>,
+                       ReadExpect<'a, Entity>,
+                       WriteStorage<'a, Skills>,
+                       ReadStorage<'a, Encumbrance>);
 
     fn run(&mut self, data : Self::SystemData) {
         let (
@@ -29,11 +47,16 @@ impl<'a> System<'a> for MeleeCombatSystem {
             mut wants_to_melee,
             names,
             combat_stats,
-            mut suffer_damage,
+            mut effects,
             mut game_log,
             melee_power_bonuses,
             defense_bonuses,
-            equipped
+            equipped,
+            attributes,
+            mut rng,
+            player_entity,
+            mut skills,
+            encumbrance
         ) = data;
 
         for (
@@ -74,13 +97,65 @@ impl<'a> System<'a> for MeleeCombatSystem {
                         }
                     }
 
+                    let target_name = names.get(wants_to_melee.target).unwrap();
+
+                    // Roll to hit with the attacker's melee skill against the
+                    // target's armor class, improved by their defense skill
+                    let mut might_bonus = 0;
+                    if let Some(attr) = attributes.get(entity) {
+                        might_bonus += damage_bonus(attr.might.total());
+                    }
+                    let mut target_ac = armor_class(10);
+                    if let Some(attr) = attributes.get(wants_to_melee.target) {
+                        target_ac = armor_class(attr.quickness.total());
+                    }
+                    if let Some(target_skills) = skills.get(wants_to_melee.target) {
+                        target_ac += target_skills.rank(Skill::Defense);
+                    }
+                    // Heavy loads make it harder both to swing and to dodge
+                    if let Some(burden) = encumbrance.get(wants_to_melee.target) {
+                        target_ac -= burden.state.combat_penalty();
+                    }
+                    if let Some(burden) = encumbrance.get(entity) {
+                        target_ac += burden.state.combat_penalty();
+                    }
+
+                    let hit = skill_check(
+                        &mut rng,
+                        skills.get(entity),
+                        attributes.get(entity),
+                        Skill::Melee,
+                        target_ac
+                    );
+
+                    // Whoever succeeded gets better at it
+                    let (practised_by, practised) = if hit {
+                        (entity, Skill::Melee)
+                    } else {
+                        (wants_to_melee.target, Skill::Defense)
+                    };
+                    if let Some(practiser_skills) = skills.get_mut(practised_by) {
+                        if practise_skill(practiser_skills, practised) && practised_by == *player_entity {
+                            game_log.entries.push(format!(
+                                "Your {:?} skill improves to {}.",
+                                practised,
+                                practiser_skills.rank(practised)
+                            ));
+                        }
+                    }
+
+                    if !hit {
+                        game_log.entries.push(
+                            format!("{} misses {}.", &name.name, &target_name.name)
+                        );
+                        continue;
+                    }
+
                     let damage = i32::max(
                         0,
-                        (stats.power + offensive_bonus) - (target_stats.defense + defensive_bonus)
+                        (stats.power + offensive_bonus + might_bonus) - (target_stats.defense + defensive_bonus)
                     );
 
-                    let target_name = names.get(wants_to_melee.target).unwrap();
-
                     if damage == 0 {
                         game_log.entries.push(
                             format!(
@@ -98,10 +173,10 @@ impl<'a> System<'a> for MeleeCombatSystem {
                                 damage
                             )
                         );
-                        SufferDamage::new_damage(
-                            &mut suffer_damage,
-                            wants_to_melee.target,
-                            damage
+                        effects.add(
+                            Some(entity),
+                            EffectType::Damage{ amount : damage, damage_type : DamageType::Physical },
+                            Targets::Single{ target : wants_to_melee.target }
                         );
                     }
                 }
@@ -0,0 +1,177 @@
+use rltk::{ DistanceAlg, Point };
+use specs::prelude::*;
+use super::{
+    Ability,
+    aoe_tiles,
+    AreaOfEffect,
+    CombatStats,
+    ConeOfEffect,
+    cone_tiles,
+    EffectQueue,
+    EffectType,
+    GameClock,
+    GameLog,
+    InflictsDamage,
+    InflictsStatus,
+    Map,
+    map::TileType,
+    MonsterMind,
+    Name,
+    Position,
+    ProvidesHealing,
+    ranged_combat_system::line_of_fire,
+    RunState,
+    StatusEffects,
+    Summons,
+    Targets,
+    Teleports,
+    WantsToMelee,
+    WantsToMove,
+    WantsToPickupItem,
+    WantsToShoot
+};
+
+/// Runs after MonsterAI has picked each monster's ordinary action, and lets a
+/// monster swap that action for an ability when one is ready and fits the
+/// situation. Abilities are resolved as item uses by the effects queue.
+pub struct MonsterAbilitySystem {}
+
+impl<'a> System<'a> for MonsterAbilitySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Map>,
+                       ReadExpect<'a, RunState>,
+                       ReadExpect<'a, GameClock>,
+                       WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, EffectQueue>,
+                       WriteStorage<'a, Ability>,
+                       ReadStorage<'a, MonsterMind>,
+                       ReadStorage<'a, Position>,
+                       ReadStorage<'a, CombatStats>,
+                       ReadStorage<'a, StatusEffects>,
+                       ReadStorage<'a, Name>,
+                       ReadStorage<'a, ProvidesHealing>,
+                       ReadStorage<'a, Teleports>,
+                       ReadStorage<'a, Summons>,
+                       ReadStorage<'a, InflictsDamage>,
+                       ReadStorage<'a, InflictsStatus>,
+                       ReadStorage<'a, AreaOfEffect>,
+                       ReadStorage<'a, ConeOfEffect>,
+                       WriteStorage<'a, WantsToMelee>,
+                       WriteStorage<'a, WantsToShoot>,
+                       WriteStorage<'a, WantsToMove>,
+                       WriteStorage<'a, WantsToPickupItem>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (
+            entities,
+            map,
+            runstate,
+            clock,
+            mut gamelog,
+            mut effects,
+            mut abilities,
+            minds,
+            positions,
+            combat_stats,
+            statuses,
+            names,
+            healing,
+            teleports,
+            summons,
+            damaging,
+            inflicts_status,
+            aoe,
+            cones,
+            mut wants_melee,
+            mut wants_shoot,
+            mut wants_move,
+            mut wants_pickup
+        ) = data;
+
+        if *runstate != RunState::MonsterTurn { return; }
+
+        for ability in (&mut abilities).join() {
+            if ability.ready_in > 0 { ability.ready_in -= 1; }
+        }
+
+        // One ability per monster per turn
+        let mut casters : Vec<Entity> = Vec::new();
+
+        for (ability_entity, ability, ability_name) in (&entities, &mut abilities, &names).join() {
+            let caster = ability.owner;
+            if ability.ready_in > 0 || casters.contains(&caster) { continue; }
+            if statuses.get(caster).map(|s| s.prevents_action(clock.turn)).unwrap_or(false) { continue; }
+
+            let (caster_pos, stats, mind) = match (positions.get(caster), combat_stats.get(caster), minds.get(caster)) {
+                (Some(pos), Some(stats), Some(mind)) => (Point::new(pos.x, pos.y), stats, mind),
+                _ => continue
+            };
+            let target = mind.target.and_then(|t| positions.get(t).map(|p| (t, Point::new(p.x, p.y))));
+            let in_range = |target_pos : Point| {
+                DistanceAlg::Pythagoras.distance2d(caster_pos, target_pos) <= ability.range as f32 + 0.5
+            };
+
+            // Each kind of ability has its own idea of when it is worth using
+            let targets = if healing.get(ability_entity).is_some() {
+                if stats.hp > stats.max_hp / 2 { continue; }
+                Targets::Single{ target : caster }
+            } else if teleports.get(ability_entity).is_some() {
+                match target {
+                    Some((_target, target_pos)) if in_range(target_pos) => Targets::Single{ target : caster },
+                    _ => continue
+                }
+            } else if let Some(summon) = summons.get(ability_entity) {
+                match target {
+                    Some((_target, target_pos)) if in_range(target_pos) => {
+                        let mut tiles : Vec<i32> = Vec::new();
+                        for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)].iter() {
+                            let idx = map.xy_idx(caster_pos.x + dx, caster_pos.y + dy);
+                            if map.tiles[idx] != TileType::Wall && !map.blocked[idx] && (tiles.len() as i32) < summon.count {
+                                tiles.push(idx as i32);
+                            }
+                        }
+                        if tiles.is_empty() { continue; }
+                        Targets::Tiles{ tiles }
+                    }
+                    _ => continue
+                }
+            } else if damaging.get(ability_entity).is_some() || inflicts_status.get(ability_entity).is_some() {
+                let (target, target_pos) = match target {
+                    Some((target, target_pos)) if in_range(target_pos) => (target, target_pos),
+                    _ => continue
+                };
+                if cones.get(ability_entity).is_some() {
+                    Targets::Tiles{ tiles : cone_tiles(&map, caster_pos, target_pos, ability.range) }
+                } else {
+                    let (_path, hit) = line_of_fire(&map, &combat_stats, caster_pos, target_pos);
+                    if hit != Some(target) { continue; }
+                    match aoe.get(ability_entity) {
+                        None => Targets::Tile{ tile_idx : map.xy_idx(target_pos.x, target_pos.y) as i32 },
+                        Some(area) => Targets::Tiles{ tiles : aoe_tiles(&map, target_pos, area.radius) }
+                    }
+                }
+            } else {
+                continue;
+            };
+
+            effects.add(Some(caster), EffectType::ItemUse{ item : ability_entity }, targets);
+            ability.ready_in = ability.cooldown;
+            casters.push(caster);
+
+            if map.visible_tiles[map.xy_idx(caster_pos.x, caster_pos.y)] {
+                if let Some(name) = names.get(caster) {
+                    gamelog.entries.push(format!("{} uses {}!", name.name, ability_name.name));
+                }
+            }
+        }
+
+        // Using an ability takes the place of whatever the monster was about to do
+        for caster in casters.iter() {
+            wants_melee.remove(*caster);
+            wants_shoot.remove(*caster);
+            wants_move.remove(*caster);
+            wants_pickup.remove(*caster);
+        }
+    }
+}
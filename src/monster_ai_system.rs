@@ -1,6 +1,62 @@
 use specs::prelude::*;
-use super::{ Confusion, Map, Monster, Position, Viewshed, WantsToMelee, RunState };
-use rltk::{ a_star_search, DistanceAlg, Point };
+use super::{
+    AiState,
+    Attributes,
+    CombatStats,
+    Faction,
+    GameClock,
+    GameLog,
+    Item,
+    Map,
+    Monster,
+    MonsterMind,
+    Name,
+    passive_difficulty,
+    pathing::{ approach_map, downhill_step, flee_map, PathingMaps },
+    Position,
+    practise_skill,
+    RangedWeapon,
+    ranged_combat_system::line_of_fire,
+    Reaction,
+    RunState,
+    Skill,
+    skill_check,
+    Skills,
+    StatusEffects,
+    StatusKind,
+    Viewshed,
+    WantsToMelee,
+    WantsToMove,
+    WantsToPickupItem,
+    WantsToShoot,
+    wins_initiative
+};
+use rltk::{ a_star_search, BaseMap, DistanceAlg, Point, RandomNumberGenerator };
+
+/// Walk downhill on a shared map, twice if hasted. The player's tile is never
+/// entered; anyone that close attacks instead.
+fn downhill(map : &Map, dm : &rltk::DijkstraMap, from : usize, hasted : bool, player_idx : usize) -> Option<usize> {
+    let mut step = downhill_step(map, dm, from)?;
+    if step == player_idx { return None; }
+    if hasted {
+        if let Some(second) = downhill_step(map, dm, step) {
+            if second != player_idx { step = second; }
+        }
+    }
+    Some(step)
+}
+
+/// Next tile on the way from one tile to another, if there is a way
+fn path_step(map : &Map, from : usize, to : usize, hasted : bool) -> Option<usize> {
+    let path = a_star_search(from, to, map);
+    if path.success && path.steps.len() > 1 {
+        // Hasted monsters cover two tiles, stopping short of their prey
+        let step = if hasted && path.steps.len() > 3 { 2 } else { 1 };
+        Some(path.steps[step])
+    } else {
+        None
+    }
+}
 
 pub struct MonsterAI {}
 
@@ -8,81 +64,283 @@ impl<'a> System<'a> for MonsterAI {
     #[allow(clippy::type_complexity)]
     type SystemData = (WriteExpect<'a, Map>,
                        ReadExpect<'a, Point>,
-                       ReadExpect<'a, Entity>,
                        ReadExpect<'a, RunState>,
                        Entities<'a>,
-                       WriteStorage<'a, Viewshed>,
+                       ReadStorage<'a, Viewshed>,
                        ReadStorage<'a, Monster>,
-                       WriteStorage<'a, Position>,
+                       ReadStorage<'a, Position>,
                        WriteStorage<'a, WantsToMelee>,
-                       WriteStorage<'a, Confusion>);
+                       ReadStorage<'a, StatusEffects>,
+                       ReadStorage<'a, RangedWeapon>,
+                       ReadStorage<'a, CombatStats>,
+                       WriteStorage<'a, WantsToShoot>,
+                       ReadExpect<'a, GameClock>,
+                       WriteStorage<'a, MonsterMind>,
+                       WriteExpect<'a, RandomNumberGenerator>,
+                       WriteExpect<'a, GameLog>,
+                       ReadStorage<'a, Name>,
+                       ReadStorage<'a, Faction>,
+                       ReadExpect<'a, PathingMaps>,
+                       ReadStorage<'a, Item>,
+                       WriteStorage<'a, WantsToPickupItem>,
+                       WriteStorage<'a, WantsToMove>,
+                       ReadExpect<'a, Entity>,
+                       ReadStorage<'a, Attributes>,
+                       WriteStorage<'a, Skills>);
 
     fn run(&mut self, data : Self::SystemData) {
         let (
             mut map,
             player_pos,
-            player_entity,
             runstate,
             entities,
-            mut viewshed,
+            viewshed,
             monster,
-            mut pos,
+            pos,
             mut wants_to_melee,
-            mut confused
+            statuses,
+            ranged_weapons,
+            combat_stats,
+            mut wants_to_shoot,
+            clock,
+            mut minds,
+            mut rng,
+            mut gamelog,
+            names,
+            factions,
+            pathing,
+            items,
+            mut wants_pickup,
+            mut wants_move,
+            player_entity,
+            attributes,
+            mut skills
         ) = data;
 
         if *runstate != RunState::MonsterTurn { return; }
 
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+        // Everything that can be fought or feared, and where it stands
+        let creatures : Vec<(Entity, Point, Faction)> = (&entities, &pos, &factions, &combat_stats).join()
+            .map(|(e, p, f, _s)| (e, Point::new(p.x, p.y), f.clone()))
+            .collect();
+
+        let mut sneaked = false;
         for (
             entity,
-            mut viewshed,
+            viewshed,
             _monster,
-            mut pos
+            pos,
+            mind
         ) in (
             &entities,
-            &mut viewshed,
+            &viewshed,
             &monster,
-            &mut pos
+            &pos,
+            &mut minds
         ).join() {
-            let mut can_act = true;
+            if statuses.get(entity).map(|s| s.prevents_action(clock.turn)).unwrap_or(false) { continue; }
+            let hasted = statuses.get(entity).map(|s| s.has(StatusKind::Haste)).unwrap_or(false);
+
+            let my_faction = match factions.get(entity) {
+                None => continue,
+                Some(faction) => faction.faction
+            };
+            let my_pos = Point::new(pos.x, pos.y);
+            let my_idx = map.xy_idx(pos.x, pos.y);
+            let badly_hurt = combat_stats.get(entity).map(|s| s.hp <= s.max_hp / 4).unwrap_or(false);
+
+            // Pick the nearest visible enemy, and note anything worth running from
+            let mut target : Option<(Entity, Point, f32)> = None;
+            let mut threats : Vec<usize> = Vec::new();
+            for (other, other_pos, other_faction) in creatures.iter() {
+                if *other == entity || !viewshed.visible_tiles.contains(other_pos) { continue; }
 
-            let is_confused = confused.get_mut(entity);
-            if let Some(i_am_confused) = is_confused {
-                i_am_confused.turns -= 1;
-                if i_am_confused.turns < 1 {
-                    confused.remove(entity);
+                // A player it isn't already after may slip by unnoticed
+                let hostile = my_faction.reaction_to(other_faction.faction) == Reaction::Attack;
+                if *other == *player_entity && hostile && mind.target != Some(*player_entity) {
+                    let difficulty = passive_difficulty(skills.get(entity), attributes.get(entity), Skill::Perception);
+                    if skill_check(&mut rng, skills.get(*other), attributes.get(*other), Skill::Stealth, difficulty) {
+                        sneaked = true;
+                        continue;
+                    }
+                }
+
+                let distance = DistanceAlg::Pythagoras.distance2d(my_pos, *other_pos);
+                match my_faction.reaction_to(other_faction.faction) {
+                    Reaction::Attack => {
+                        if badly_hurt { threats.push(map.xy_idx(other_pos.x, other_pos.y)); }
+                        if target.map(|t| distance < t.2).unwrap_or(true) {
+                            target = Some((*other, *other_pos, distance));
+                        }
+                    }
+                    Reaction::Flee => threats.push(map.xy_idx(other_pos.x, other_pos.y)),
+                    Reaction::Ignore => {}
                 }
-                can_act = false;
             }
 
-            if can_act {
-                let distance = DistanceAlg::Pythagoras.distance2d(
-                    Point::new(pos.x, pos.y),
-                    *player_pos
-                );
-
-                if distance < 1.5 {
-                    wants_to_melee.insert(
-                        entity,
-                        WantsToMelee { target : *player_entity }
-                    ).expect("Unable to insert attack");
+            // Decide what to do based on what the monster can see and remembers
+            mind.target = target.map(|t| t.0);
+            if !threats.is_empty() {
+                if badly_hurt && mind.state != AiState::Flee {
+                    if let Some(name) = names.get(entity) {
+                        gamelog.entries.push(format!("{} turns to flee!", name.name));
+                    }
+                }
+                mind.state = AiState::Flee;
+            } else if let Some((target_entity, target_pos, _distance)) = target {
+                mind.last_seen = Some(target_pos);
+                let picking_a_fight = matches!(mind.state, AiState::Idle | AiState::Wander);
+                mind.state = AiState::Chase;
+
+                // When a fight breaks out, the quicker side gets the first move
+                if picking_a_fight && !wins_initiative(&mut rng, attributes.get(entity), attributes.get(target_entity)) {
+                    if target_entity == *player_entity {
+                        if let Some(name) = names.get(entity) {
+                            gamelog.entries.push(format!("{} is caught off guard!", name.name));
+                        }
+                    }
+                    continue;
                 }
-                else if viewshed.visible_tiles.contains(&*player_pos) {
-                    let path = a_star_search(
-                        map.xy_idx(pos.x, pos.y),
-                        map.xy_idx(player_pos.x, player_pos.y),
-                        &mut *map
-                    );
-
-                    if path.success && path.steps.len() > 1 {
-                        let mut idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = false;
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
-                        idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = true;
-                        viewshed.dirty = true;
+            } else {
+                match mind.state {
+                    AiState::Chase => mind.state = AiState::Search,
+                    AiState::Flee => {
+                        mind.state = AiState::Idle;
+                        mind.last_seen = None;
                     }
+                    AiState::Idle => if rng.roll_dice(1, 3) == 1 { mind.state = AiState::Wander },
+                    AiState::Wander => if rng.roll_dice(1, 6) == 1 { mind.state = AiState::Idle },
+                    AiState::Search => {}
+                }
+            }
+
+            let destination = match mind.state {
+                AiState::Idle | AiState::Wander if my_faction.follows_player() &&
+                    DistanceAlg::Pythagoras.distance2d(my_pos, *player_pos) > 3.0 => {
+                    downhill(&map, &pathing.to_player, my_idx, hasted, player_idx)
+                }
+                AiState::Idle | AiState::Wander if my_faction.scavenges() &&
+                    pathing.to_items.map[my_idx] < f32::MAX => {
+                    // Pick up anything lying here, otherwise head for the nearest item
+                    let here = map.tile_content[my_idx].iter().find(|e| items.get(**e).is_some());
+                    match here {
+                        Some(item) => {
+                            wants_pickup.insert(
+                                entity,
+                                WantsToPickupItem{ collected_by : entity, item : *item }
+                            ).expect("Unable to insert want to pickup");
+                            None
+                        }
+                        None => downhill(&map, &pathing.to_items, my_idx, hasted, player_idx)
+                    }
+                }
+                AiState::Idle => None,
+                AiState::Wander => {
+                    // The player's tile is not marked as blocked, so keep out of it
+                    let exits : Vec<usize> = map.get_available_exits(my_idx).iter()
+                        .map(|exit| exit.0)
+                        .filter(|idx| *idx != player_idx)
+                        .collect();
+                    if exits.is_empty() {
+                        None
+                    } else {
+                        Some(exits[(rng.roll_dice(1, exits.len() as i32) - 1) as usize])
+                    }
+                }
+                AiState::Chase => {
+                    let (target, target_pos, distance) = match target {
+                        None => continue,
+                        Some(target) => target
+                    };
+                    if distance < 1.5 {
+                        wants_to_melee.insert(
+                            entity,
+                            WantsToMelee { target }
+                        ).expect("Unable to insert attack");
+                        continue;
+                    }
+
+                    // Shoot if there is a clear shot, otherwise close the distance
+                    if let Some(weapon) = ranged_weapons.get(entity) {
+                        if distance <= weapon.range as f32 {
+                            let (_path, hit) = line_of_fire(&map, &combat_stats, my_pos, target_pos);
+                            if hit == Some(target) {
+                                wants_to_shoot.insert(
+                                    entity,
+                                    WantsToShoot { target }
+                                ).expect("Unable to insert shot");
+                                continue;
+                            }
+                        }
+                    }
+
+                    // Everyone chasing the player shares one map
+                    let target_idx = map.xy_idx(target_pos.x, target_pos.y);
+                    if target_idx == player_idx {
+                        downhill(&map, &pathing.to_player, my_idx, hasted, player_idx)
+                    } else {
+                        // The target's own tile is blocked, which would stop A* reaching it
+                        let was_blocked = map.blocked[target_idx];
+                        map.blocked[target_idx] = false;
+                        let step = path_step(&map, my_idx, target_idx, hasted);
+                        map.blocked[target_idx] = was_blocked;
+                        step
+                    }
+                }
+                AiState::Flee => {
+                    // Only the player is common enough to be worth a shared map
+                    let step = if threats == [player_idx] {
+                        downhill(&map, &pathing.from_player, my_idx, false, player_idx)
+                    } else {
+                        let approach = approach_map(&mut map, &threats, 200.0);
+                        let away = flee_map(&mut map, &approach);
+                        downhill(&map, &away, my_idx, false, player_idx)
+                    };
+                    match step {
+                        Some(step) => Some(step),
+                        None => {
+                            // Cornered: fight back
+                            if let Some((target, _target_pos, distance)) = target {
+                                if distance < 1.5 {
+                                    wants_to_melee.insert(
+                                        entity,
+                                        WantsToMelee { target }
+                                    ).expect("Unable to insert attack");
+                                }
+                            }
+                            None
+                        }
+                    }
+                }
+                AiState::Search => {
+                    // Head for where the quarry was last seen, and give up on arrival
+                    let step = match mind.last_seen {
+                        None => None,
+                        Some(last_seen) => path_step(&map, my_idx, map.xy_idx(last_seen.x, last_seen.y), hasted)
+                    };
+                    if step.is_none() {
+                        mind.state = AiState::Idle;
+                        mind.last_seen = None;
+                    }
+                    step
+                }
+            };
+
+            if let Some(destination) = destination {
+                wants_move.insert(
+                    entity,
+                    WantsToMove{ destination : Point::new(destination as i32 % map.width, destination as i32 / map.width) }
+                ).expect("Unable to insert movement intent");
+            }
+        }
+
+        // Sneaking past any number of watchers in a turn counts as one use
+        if sneaked {
+            if let Some(player_skills) = skills.get_mut(*player_entity) {
+                if practise_skill(player_skills, Skill::Stealth) {
+                    gamelog.entries.push(format!("Your {:?} skill improves to {}.", Skill::Stealth, player_skills.rank(Skill::Stealth)));
                 }
             }
         }
@@ -0,0 +1,70 @@
+use rltk::Point;
+use specs::prelude::*;
+use super::{ BlocksTile, EntityMoved, Map, Position, Viewshed, WantsToMove };
+
+/// Resolves movement intents one at a time against the live blocked list, so
+/// two creatures can never end up sharing a tile. A creature whose destination
+/// was taken first sidesteps to another free tile next to it, or waits.
+pub struct MovementSystem {}
+
+impl<'a> System<'a> for MovementSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (WriteExpect<'a, Map>,
+                       ReadExpect<'a, Point>,
+                       Entities<'a>,
+                       WriteStorage<'a, WantsToMove>,
+                       WriteStorage<'a, Position>,
+                       WriteStorage<'a, Viewshed>,
+                       ReadStorage<'a, BlocksTile>,
+                       WriteStorage<'a, EntityMoved>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (
+            mut map,
+            player_pos,
+            entities,
+            mut wants_move,
+            mut positions,
+            mut viewsheds,
+            blockers,
+            mut moved
+        ) = data;
+
+        // The player doesn't block tiles, but nobody else may stand on them
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+        for (entity, intent, pos) in (&entities, &wants_move, &mut positions).join() {
+            let from = map.xy_idx(pos.x, pos.y);
+            let destination = map.xy_idx(intent.destination.x, intent.destination.y);
+
+            let chosen = if !map.blocked[destination] && destination != player_idx {
+                Some(destination)
+            } else {
+                rltk::BaseMap::get_available_exits(&*map, from).iter()
+                    .map(|exit| exit.0)
+                    .find(|idx| {
+                        let x = *idx as i32 % map.width;
+                        let y = *idx as i32 / map.width;
+                        *idx != player_idx &&
+                            i32::abs(x - intent.destination.x) <= 1 &&
+                            i32::abs(y - intent.destination.y) <= 1
+                    })
+            };
+
+            if let Some(to) = chosen {
+                if blockers.get(entity).is_some() {
+                    map.blocked[from] = false;
+                    map.blocked[to] = true;
+                }
+                pos.x = to as i32 % map.width;
+                pos.y = to as i32 / map.width;
+                if let Some(viewshed) = viewsheds.get_mut(entity) {
+                    viewshed.dirty = true;
+                }
+                moved.insert(entity, EntityMoved{}).expect("Unable to insert marker");
+            }
+        }
+
+        wants_move.clear();
+    }
+}
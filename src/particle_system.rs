@@ -0,0 +1,22 @@
+use rltk::Rltk;
+use specs::prelude::*;
+use super::ParticleLifetime;
+
+/// Particles are purely cosmetic and live for a fixed amount of real time,
+/// regardless of how many turns pass
+pub fn cull_dead_particles(ecs : &mut World, ctx : &Rltk) {
+    let mut dead_particles : Vec<Entity> = Vec::new();
+    {
+        let entities = ecs.entities();
+        let mut particles = ecs.write_storage::<ParticleLifetime>();
+        for (entity, particle) in (&entities, &mut particles).join() {
+            particle.lifetime_ms -= ctx.frame_time_ms;
+            if particle.lifetime_ms < 0.0 {
+                dead_particles.push(entity);
+            }
+        }
+    }
+    for dead in dead_particles.iter() {
+        ecs.delete_entity(*dead).expect("Particle will not die");
+    }
+}
@@ -0,0 +1,184 @@
+use rltk::{ DijkstraMap, Point };
+use specs::prelude::*;
+use super::{ Item, Map, Position, RunState };
+
+/// Paths longer than this are treated as unreachable
+const MAX_DEPTH : f32 = 200.0;
+
+/// Items further than this don't tempt scavengers
+const ITEM_DEPTH : f32 = 8.0;
+
+/// Dijkstra maps shared by every monster, rebuilt once per monster turn.
+/// Each is read by stepping "downhill" towards lower values.
+pub struct PathingMaps {
+    pub to_player : DijkstraMap,
+    pub from_player : DijkstraMap,
+    pub to_items : DijkstraMap
+}
+
+impl PathingMaps {
+    pub fn new(map : &Map) -> PathingMaps {
+        PathingMaps{
+            to_player : DijkstraMap::new_empty(map.width, map.height, MAX_DEPTH),
+            from_player : DijkstraMap::new_empty(map.width, map.height, MAX_DEPTH),
+            to_items : DijkstraMap::new_empty(map.width, map.height, ITEM_DEPTH)
+        }
+    }
+}
+
+/// Build a Dijkstra map over the terrain alone. Creatures are ignored so that
+/// the tiles they stand on still get a distance; the live blocked list is only
+/// consulted when a step is actually taken.
+fn terrain_dijkstra(map : &mut Map, starts : &[(usize, f32)], max_depth : f32) -> DijkstraMap {
+    let occupied = map.blocked.clone();
+    map.populate_blocked();
+    let mut dm = DijkstraMap::new_weighted(map.width, map.height, starts, &*map, max_depth);
+    map.blocked = occupied;
+
+    // rltk leaves the starting tiles themselves unscored
+    for (idx, weight) in starts.iter() {
+        dm.map[*idx] = f32::min(dm.map[*idx], *weight);
+    }
+    dm
+}
+
+/// Distance to the nearest of the target tiles
+pub fn approach_map(map : &mut Map, targets : &[usize], max_depth : f32) -> DijkstraMap {
+    let starts : Vec<(usize, f32)> = targets.iter().map(|idx| (*idx, 0.0)).collect();
+    terrain_dijkstra(map, &starts, max_depth)
+}
+
+/// Invert an approach map and rescan it, so that walking downhill leads away
+/// from the targets towards open space rather than into the nearest dead end
+pub fn flee_map(map : &mut Map, approach : &DijkstraMap) -> DijkstraMap {
+    let starts : Vec<(usize, f32)> = approach.map.iter()
+        .enumerate()
+        .filter(|(_idx, depth)| **depth < f32::MAX)
+        .map(|(idx, depth)| (idx, *depth * -1.2))
+        .collect();
+    terrain_dijkstra(map, &starts, MAX_DEPTH)
+}
+
+/// The neighbouring open tile that is strictly downhill from here, if any
+pub fn downhill_step(map : &Map, dm : &DijkstraMap, from : usize) -> Option<usize> {
+    match DijkstraMap::find_lowest_exit(dm, from, map) {
+        Some(exit) if dm.map[exit] < dm.map[from] => Some(exit),
+        _ => None
+    }
+}
+
+pub struct PathingSystem {}
+
+impl<'a> System<'a> for PathingSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (WriteExpect<'a, Map>,
+                       ReadExpect<'a, Point>,
+                       ReadExpect<'a, RunState>,
+                       WriteExpect<'a, PathingMaps>,
+                       ReadStorage<'a, Item>,
+                       ReadStorage<'a, Position>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut map, player_pos, runstate, mut pathing, items, positions) = data;
+
+        if *runstate != RunState::MonsterTurn { return; }
+
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        pathing.to_player = approach_map(&mut map, &[player_idx], MAX_DEPTH);
+        pathing.from_player = flee_map(&mut map, &pathing.to_player);
+
+        let item_tiles : Vec<usize> = (&items, &positions).join()
+            .map(|(_item, pos)| map.xy_idx(pos.x, pos.y))
+            .collect();
+        pathing.to_items = approach_map(&mut map, &item_tiles, ITEM_DEPTH);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rltk::{ a_star_search, RandomNumberGenerator };
+    use std::time::Instant;
+    use super::*;
+    use crate::map::{ MAPCOUNT, TileType };
+
+    #[test]
+    fn walking_downhill_reaches_the_target() {
+        let mut map = Map::new_map_rooms_and_corridors(1);
+        map.populate_blocked();
+        let target = map.rooms[0].center();
+        let target_idx = map.xy_idx(target.0, target.1);
+        let to_target = approach_map(&mut map, &[target_idx], MAX_DEPTH);
+
+        let start = map.rooms[map.rooms.len() - 1].center();
+        let mut at = map.xy_idx(start.0, start.1);
+        let mut steps = 0;
+        while let Some(next) = downhill_step(&map, &to_target, at) {
+            assert!(to_target.map[next] < to_target.map[at]);
+            at = next;
+            steps += 1;
+            assert!(steps < MAPCOUNT, "walked in circles");
+        }
+        assert_eq!(at, target_idx);
+    }
+
+    #[test]
+    fn fleeing_moves_further_away() {
+        let mut map = Map::new_map_rooms_and_corridors(1);
+        map.populate_blocked();
+        let target = map.rooms[0].center();
+        let target_idx = map.xy_idx(target.0, target.1);
+        let to_target = approach_map(&mut map, &[target_idx], MAX_DEPTH);
+        let from_target = flee_map(&mut map, &to_target);
+
+        let next = downhill_step(&map, &from_target, target_idx).expect("nowhere to run");
+        assert!(to_target.map[next] > to_target.map[target_idx]);
+    }
+
+    /// Compare per-monster A* against one shared Dijkstra map on a crowded level.
+    /// Run with `cargo test --release -- --ignored --nocapture pathing_benchmark`.
+    #[test]
+    #[ignore]
+    fn pathing_benchmark() {
+        const MONSTERS : usize = 60;
+        const TURNS : u32 = 5;
+
+        let mut map = Map::new_map_rooms_and_corridors(10);
+        map.populate_blocked();
+        let mut rng = RandomNumberGenerator::seeded(1);
+
+        let player = map.rooms[0].center();
+        let player_idx = map.xy_idx(player.0, player.1);
+        let mut monsters : Vec<usize> = Vec::new();
+        while monsters.len() < MONSTERS {
+            let idx = map.xy_idx(rng.roll_dice(1, map.width - 2), rng.roll_dice(1, map.height - 2));
+            if map.tiles[idx] == TileType::Floor && !map.blocked[idx] && idx != player_idx {
+                map.blocked[idx] = true;
+                monsters.push(idx);
+            }
+        }
+
+        let start = Instant::now();
+        let mut a_star_steps = 0;
+        for _turn in 0 .. TURNS {
+            for monster in monsters.iter() {
+                let path = a_star_search(*monster, player_idx, &map);
+                if path.success && path.steps.len() > 1 { a_star_steps += 1; }
+            }
+        }
+        let a_star_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut dijkstra_steps = 0;
+        for _turn in 0 .. TURNS {
+            let to_player = approach_map(&mut map, &[player_idx], MAX_DEPTH);
+            for monster in monsters.iter() {
+                if downhill_step(&map, &to_player, *monster).is_some() { dijkstra_steps += 1; }
+            }
+        }
+        let dijkstra_time = start.elapsed();
+
+        println!("{} monsters, {} turns on a depth 10 map", MONSTERS, TURNS);
+        println!("A* per monster:        {:>8.2} ms ({} moves)", a_star_time.as_secs_f64() * 1000.0, a_star_steps);
+        println!("Shared Dijkstra map:   {:>8.2} ms ({} moves)", dijkstra_time.as_secs_f64() * 1000.0, dijkstra_steps);
+    }
+}
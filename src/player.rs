@@ -1,30 +1,83 @@
-use rltk::{ Point, Rltk, VirtualKeyCode };
+use rltk::{ Point, RandomNumberGenerator, Rltk, VirtualKeyCode };
 use specs::prelude::*;
 use super::{ 
+    Ammunition,
+    AreaOfEffect,
+    Attributes,
     CombatStats,
+    Container,
+    Encumbrance,
+    EncumbranceState,
+    EffectQueue,
+    EffectType,
+    EntityMoved,
+    Equipped,
+    Faction,
+    FactionKind,
     GameLog,
+    GoalItem,
+    Hidden,
+    gui::{ AttributeChoice, VendorMode },
+    InBackpack,
     Item,
+    Locked,
     Map,
     Monster,
+    Name,
     Player,
+    player_hp_at_level,
     Position,
+    practise_skill,
+    RangedWeapon,
+    Reaction,
     RunState,
+    Skill,
+    skill_check,
+    Skills,
+    Stackable,
     State,
+    StatusEffects,
+    StatusKind,
     map::TileType,
+    Targets,
+    Trap,
+    trap_targets,
+    Trapped,
+    Vendor,
     Viewshed,
     WantsToMelee,
-    WantsToPickupItem
+    WantsToPickupItem,
+    xp_to_next_level
 };
 use std::cmp::{ min, max };
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// How far around them the player looks when searching for traps
+const SEARCH_RADIUS : i32 = 3;
+
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
+    // Confusion sends the player stumbling in a random direction
+    let (delta_x, delta_y) = if player_has_status(ecs, StatusKind::Confusion) {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let mut stumble = (0, 0);
+        while stumble == (0, 0) {
+            stumble = (rng.range(-1, 2), rng.range(-1, 2));
+        }
+        stumble
+    } else {
+        (delta_x, delta_y)
+    };
+
     let mut positions = ecs.write_storage::<Position>();
     let players = ecs.read_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let factions = ecs.read_storage::<Faction>();
+    let vendors = ecs.read_storage::<Vendor>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut moved = ecs.write_storage::<EntityMoved>();
     let map = ecs.fetch::<Map>();
     let entities = ecs.entities();
+    let mut swap : Option<(Entity, Point)> = None;
 
     for (
         entity,
@@ -42,22 +95,36 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
 
         if new_x < 1 || new_x > map.width - 1 ||
             new_y  < 1 || new_y > map.height - 1 {
-            return;
+            return RunState::PlayerTurn;
         }
 
         let destination_idx = map.xy_idx(new_x, new_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            // Walking into a shopkeeper opens their shop
+            if vendors.get(*potential_target).is_some() {
+                return RunState::ShowVendor{ vendor : *potential_target, mode : VendorMode::Buy };
+            }
+
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
+                // Allies trade places rather than being attacked
+                let friendly = factions.get(*potential_target)
+                    .map(|f| FactionKind::Player.reaction_to(f.faction) == Reaction::Ignore)
+                    .unwrap_or(false);
+                if friendly {
+                    swap = Some((*potential_target, Point::new(pos.x, pos.y)));
+                    break;
+                }
+
                 wants_to_melee.
                     insert(entity, WantsToMelee { target : *potential_target }).
                     expect("Add target failed");
-                return;
+                return RunState::PlayerTurn;
             }
         }
 
-        if !map.blocked[destination_idx] {
+        if !map.blocked[destination_idx] || swap.is_some() {
             pos.x = min(79, max(0, new_x));
             pos.y = min(49, max(0, new_y));
 
@@ -66,8 +133,22 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             ppos.y = pos.y;
 
             viewshed.dirty = true;
+            moved.insert(entity, EntityMoved{}).expect("Unable to insert marker");
+        }
+    }
+
+    if let Some((ally, old_pos)) = swap {
+        if let Some(ally_pos) = positions.get_mut(ally) {
+            ally_pos.x = old_pos.x;
+            ally_pos.y = old_pos.y;
+        }
+        if let Some(ally_viewshed) = viewsheds.get_mut(ally) {
+            ally_viewshed.dirty = true;
         }
+        moved.insert(ally, EntityMoved{}).expect("Unable to insert marker");
     }
+
+    RunState::PlayerTurn
 }
 
 fn get_item(ecs : &mut World) {
@@ -96,6 +177,153 @@ fn get_item(ecs : &mut World) {
     }
 }
 
+/// The player gets better at a skill by using it successfully
+fn practise(ecs : &mut World, skill : Skill) {
+    let player_entity = *ecs.fetch::<Entity>();
+    if let Some(skills) = ecs.write_storage::<Skills>().get_mut(player_entity) {
+        if practise_skill(skills, skill) {
+            ecs.fetch_mut::<GameLog>().entries.push(format!("Your {:?} skill improves to {}.", skill, skills.rank(skill)));
+        }
+    }
+}
+
+/// Spend a turn looking closely for hidden traps nearby
+fn search(ecs : &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let found : Vec<(Entity, String)> = {
+        let player_pos = *ecs.fetch::<Point>();
+        let map = ecs.fetch::<Map>();
+        let entities = ecs.entities();
+        let hidden = ecs.read_storage::<Hidden>();
+        let traps = ecs.read_storage::<Trap>();
+        let positions = ecs.read_storage::<Position>();
+        let names = ecs.read_storage::<Name>();
+        let skills = ecs.read_storage::<Skills>();
+        let attributes = ecs.read_storage::<Attributes>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        (&entities, &hidden, &traps, &positions).join()
+            .filter(|(_entity, _hidden, _trap, pos)| {
+                map.visible_tiles[map.xy_idx(pos.x, pos.y)] &&
+                    i32::abs(pos.x - player_pos.x) <= SEARCH_RADIUS && i32::abs(pos.y - player_pos.y) <= SEARCH_RADIUS
+            })
+            .filter(|(_entity, _hidden, trap, _pos)| {
+                skill_check(&mut rng, skills.get(player_entity), attributes.get(player_entity), Skill::Perception, trap.difficulty)
+            })
+            .map(|(entity, _hidden, _trap, _pos)| (entity, names.get(entity).map_or(String::new(), |n| n.name.clone())))
+            .collect()
+    };
+
+    if found.is_empty() {
+        ecs.fetch_mut::<GameLog>().entries.push("You search carefully, but find nothing.".to_string());
+    }
+    for (trap, name) in found.iter() {
+        ecs.write_storage::<Hidden>().remove(*trap);
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You find a {}!", name));
+        practise(ecs, Skill::Perception);
+    }
+    RunState::PlayerTurn
+}
+
+/// Try to take apart a known trap the player is standing on or next to. A
+/// clumsy attempt may set it off.
+fn disarm_trap(ecs : &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let target = {
+        let player_pos = ecs.fetch::<Point>();
+        let entities = ecs.entities();
+        let hidden = ecs.read_storage::<Hidden>();
+        let traps = ecs.read_storage::<Trap>();
+        let positions = ecs.read_storage::<Position>();
+        (&entities, &traps, &positions, !&hidden).join()
+            .find(|(_entity, _trap, pos, _hidden)| i32::abs(pos.x - player_pos.x) <= 1 && i32::abs(pos.y - player_pos.y) <= 1)
+            .map(|(entity, trap, pos, _hidden)| (entity, trap.difficulty, Point::new(pos.x, pos.y)))
+    };
+    let (trap, difficulty, trap_pos) = match target {
+        None => {
+            ecs.fetch_mut::<GameLog>().entries.push("There is no trap here to disarm.".to_string());
+            return RunState::AwaitingInput;
+        }
+        Some(target) => target
+    };
+    let name = ecs.read_storage::<Name>().get(trap).map(|n| n.name.clone()).unwrap_or_default();
+
+    let (disarmed, fumbled) = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let skills = ecs.read_storage::<Skills>();
+        let attributes = ecs.read_storage::<Attributes>();
+        let disarmed = skill_check(&mut rng, skills.get(player_entity), attributes.get(player_entity), Skill::Lockpicking, difficulty);
+        (disarmed, !disarmed && rng.roll_dice(1, 3) == 1)
+    };
+
+    if disarmed {
+        ecs.entities().delete(trap).expect("Unable to delete trap");
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You disarm the {}.", name));
+        practise(ecs, Skill::Lockpicking);
+    } else if fumbled {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You set off the {}!", name));
+        let targets = {
+            let map = ecs.fetch::<Map>();
+            trap_targets(&map, ecs.read_storage::<AreaOfEffect>().get(trap), player_entity, trap_pos)
+        };
+        ecs.write_resource::<EffectQueue>().add(Some(trap), EffectType::ItemUse{ item : trap }, targets);
+    } else {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You fail to disarm the {}.", name));
+    }
+    RunState::PlayerTurn
+}
+
+/// Open a container the player is standing on or next to. Locks have to be
+/// picked first, and a trapped container goes off in the player's face.
+fn open_container(ecs : &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let container = {
+        let player_pos = ecs.fetch::<Point>();
+        let entities = ecs.entities();
+        let containers = ecs.read_storage::<Container>();
+        let positions = ecs.read_storage::<Position>();
+        (&entities, &containers, &positions).join()
+            .find(|(_entity, _container, pos)| i32::abs(pos.x - player_pos.x) <= 1 && i32::abs(pos.y - player_pos.y) <= 1)
+            .map(|(entity, _container, _pos)| entity)
+    };
+    let container = match container {
+        None => {
+            ecs.fetch_mut::<GameLog>().entries.push("There is nothing here to open.".to_string());
+            return RunState::AwaitingInput;
+        }
+        Some(container) => container
+    };
+    let name = ecs.read_storage::<Name>().get(container).map(|n| n.name.clone()).unwrap_or_default();
+
+    let lock = ecs.read_storage::<Locked>().get(container).map(|lock| lock.difficulty);
+    if let Some(difficulty) = lock {
+        let picked = {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            let skills = ecs.read_storage::<Skills>();
+            let attributes = ecs.read_storage::<Attributes>();
+            skill_check(&mut rng, skills.get(player_entity), attributes.get(player_entity), Skill::Lockpicking, difficulty)
+        };
+        if !picked {
+            ecs.fetch_mut::<GameLog>().entries.push(format!("You fail to pick the lock on the {}.", name));
+            return RunState::PlayerTurn;
+        }
+        ecs.write_storage::<Locked>().remove(container);
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You pick the lock on the {}.", name));
+        practise(ecs, Skill::Lockpicking);
+    }
+
+    if ecs.write_storage::<Trapped>().remove(container).is_some() {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("A trap on the {} goes off!", name));
+        ecs.write_resource::<EffectQueue>().add(
+            Some(container),
+            EffectType::ItemUse{ item : container },
+            Targets::Single{ target : player_entity }
+        );
+        return RunState::PlayerTurn;
+    }
+
+    RunState::ShowContainer{ container }
+}
+
 pub fn try_next_level(ecs : &mut World) -> bool {
     let player_pos = ecs.fetch::<Point>();
     let map = ecs.fetch::<Map>();
@@ -109,67 +337,206 @@ pub fn try_next_level(ecs : &mut World) -> bool {
     }
 }
 
-fn skip_turn(ecs : &mut World) -> RunState {
+/// Going up from the first level leaves the dungeon, which is only allowed
+/// once the player has what they came for
+pub fn try_previous_level(ecs : &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+    if map.tiles[player_idx] != TileType::UpStairs {
+        gamelog.entries.push("There is no way up from here.".to_string());
+        return false;
+    }
+    if map.depth == 1 && !player_has_goal(ecs) {
+        gamelog.entries.push("Daylight beckons, but you will not leave without the amulet.".to_string());
+        return false;
+    }
+    true
+}
+
+pub fn player_has_goal(ecs : &World) -> bool {
     let player_entity = ecs.fetch::<Entity>();
-    let viewshed_components = ecs.read_storage::<Viewshed>();
+    let goals = ecs.read_storage::<GoalItem>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+
+    let carried = (&goals, &backpack).join().any(|(_goal, carried)| carried.owner == *player_entity);
+    let worn = (&goals, &equipped).join().any(|(_goal, worn)| worn.owner == *player_entity);
+    carried || worn
+}
+
+pub fn player_has_status(ecs : &World, kind : StatusKind) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    match statuses.get(*player_entity) {
+        None => false,
+        Some(status) => status.has(kind)
+    }
+}
+
+/// How weighed down the player was at the end of the last turn
+pub fn player_encumbrance(ecs : &World) -> EncumbranceState {
+    let player_entity = ecs.fetch::<Entity>();
+    let encumbrance = ecs.read_storage::<Encumbrance>();
+    match encumbrance.get(*player_entity) {
+        None => EncumbranceState::Unburdened,
+        Some(encumbrance) => encumbrance.state
+    }
+}
+
+/// The ranged weapon the player has equipped, if any
+pub fn player_ranged_weapon(ecs : &World) -> Option<RangedWeapon> {
+    let player_entity = ecs.fetch::<Entity>();
+    let ranged_weapons = ecs.read_storage::<RangedWeapon>();
+    let equipped = ecs.read_storage::<Equipped>();
+
+    for (weapon, equipped_by) in (&ranged_weapons, &equipped).join() {
+        if equipped_by.owner == *player_entity {
+            return Some(weapon.clone());
+        }
+    }
+    None
+}
+
+/// Monsters the player can see within the given range, nearest first
+pub fn ranged_targets(ecs : &World, range : i32) -> Vec<Entity> {
+    let player_entity = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
     let monsters = ecs.read_storage::<Monster>();
+    let factions = ecs.read_storage::<Faction>();
+    let map = ecs.fetch::<Map>();
 
-    let worldmap_resource = ecs.fetch::<Map>();
-
-    let mut can_heal = true;
-    let viewshed = viewshed_components.get(*player_entity).unwrap();
-    for tile in viewshed.visible_tiles.iter() {
-        let idx = worldmap_resource.xy_idx(tile.x, tile.y);
-        for entity_id in worldmap_resource.tile_content[idx].iter() {
-            let mob = monsters.get(*entity_id);
-            match mob {
-                None => {}
-                Some(_) => { can_heal = false; }
+    let mut targets : Vec<(f32, Entity)> = Vec::new();
+    if let Some(viewshed) = viewsheds.get(*player_entity) {
+        for tile in viewshed.visible_tiles.iter() {
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *tile);
+            if distance > range as f32 { continue; }
+            let idx = map.xy_idx(tile.x, tile.y);
+            for entity in map.tile_content[idx].iter() {
+                let hostile = factions.get(*entity)
+                    .map(|f| FactionKind::Player.reaction_to(f.faction) == Reaction::Attack)
+                    .unwrap_or(true);
+                if monsters.get(*entity).is_some() && hostile {
+                    targets.push((distance, *entity));
+                }
             }
         }
     }
 
-    if can_heal {
-        let mut health_components = ecs.write_storage::<CombatStats>();
-        let player_hp = health_components.get_mut(*player_entity).unwrap();
-        player_hp.hp = i32::min(player_hp.hp + 1, player_hp.max_hp);
+    targets.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    targets.iter().map(|t| t.1).collect()
+}
+
+/// Ready the equipped ranged weapon, if there is one and something to shoot
+fn fire_weapon(ecs : &mut World) -> RunState {
+    let weapon = player_ranged_weapon(ecs);
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    let weapon = match weapon {
+        None => {
+            gamelog.entries.push("You have no ranged weapon equipped.".to_string());
+            return RunState::AwaitingInput;
+        }
+        Some(weapon) => weapon
+    };
+
+    {
+        let player_entity = ecs.fetch::<Entity>();
+        let backpack = ecs.read_storage::<InBackpack>();
+        let ammunition = ecs.read_storage::<Ammunition>();
+        let stacks = ecs.read_storage::<Stackable>();
+        let has_ammo = (&backpack, &ammunition, &stacks).join()
+            .any(|(pack, ammo, stack)| pack.owner == *player_entity && ammo.ammo == weapon.ammo && stack.quantity > 0);
+        if !has_ammo {
+            gamelog.entries.push(format!("You have no {:?}s to shoot.", weapon.ammo));
+            return RunState::AwaitingInput;
+        }
+    }
+
+    match ranged_targets(ecs, weapon.range).first() {
+        None => {
+            gamelog.entries.push("There is nothing in range to shoot at.".to_string());
+            RunState::AwaitingInput
+        }
+        Some(target) => RunState::ShowFireTarget{ target : *target }
+    }
+}
+
+/// True when the player has banked enough experience for the next level
+pub fn can_level_up(ecs : &World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    match combat_stats.get(*player_entity) {
+        None => false,
+        Some(stats) => stats.xp >= xp_to_next_level(stats.level)
+    }
+}
+
+/// Advance the player one level, raising the chosen attribute and
+/// restoring them to full health
+pub fn level_up(ecs : &mut World, choice : AttributeChoice) {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut attributes = ecs.write_storage::<Attributes>();
+    let mut combat_stats = ecs.write_storage::<CombatStats>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let mut fitness = 10;
+    if let Some(attr) = attributes.get_mut(*player_entity) {
+        match choice {
+            AttributeChoice::Might => attr.might.base += 1,
+            AttributeChoice::Fitness => attr.fitness.base += 1,
+            AttributeChoice::Quickness => attr.quickness.base += 1,
+            AttributeChoice::Intelligence => attr.intelligence.base += 1
+        }
+        fitness = attr.fitness.total();
     }
 
-    return RunState::PlayerTurn;
+    if let Some(stats) = combat_stats.get_mut(*player_entity) {
+        stats.level += 1;
+        stats.max_hp = player_hp_at_level(fitness, stats.level);
+        stats.hp = stats.max_hp;
+        gamelog.entries.push(format!("Congratulations, you are now level {}!", stats.level));
+    }
 }
 
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+    if player_has_status(&gs.ecs, StatusKind::Paralysis) {
+        gs.ecs.fetch_mut::<GameLog>().entries.push("You are paralysed and cannot act!".to_string());
+        return RunState::PlayerTurn;
+    }
+
     match ctx.key {
         None => { return RunState::AwaitingInput},
         Some(key) => match key {
             VirtualKeyCode::Left |
             VirtualKeyCode::Numpad4 |
-            VirtualKeyCode::H => try_move_player(-1, 0, &mut gs.ecs),
+            VirtualKeyCode::H => return try_move_player(-1, 0, &mut gs.ecs),
 
             VirtualKeyCode::Right |
             VirtualKeyCode::Numpad6 |
-            VirtualKeyCode::L => try_move_player(1, 0, &mut gs.ecs),
+            VirtualKeyCode::L => return try_move_player(1, 0, &mut gs.ecs),
 
             VirtualKeyCode::Up |
             VirtualKeyCode::Numpad8 |
-            VirtualKeyCode::K => try_move_player(0, -1, &mut gs.ecs),
+            VirtualKeyCode::K => return try_move_player(0, -1, &mut gs.ecs),
 
             VirtualKeyCode::Down |
             VirtualKeyCode::Numpad2 |
-            VirtualKeyCode::J => try_move_player(0, 1, &mut gs.ecs),
+            VirtualKeyCode::J => return try_move_player(0, 1, &mut gs.ecs),
 
             // Diagonals
             VirtualKeyCode::Numpad9 |
-            VirtualKeyCode::U => try_move_player(1, -1, &mut gs.ecs),
+            VirtualKeyCode::U => return try_move_player(1, -1, &mut gs.ecs),
 
             VirtualKeyCode::Numpad7 |
-            VirtualKeyCode::Y => try_move_player(-1, -1, &mut gs.ecs),
+            VirtualKeyCode::Y => return try_move_player(-1, -1, &mut gs.ecs),
 
             VirtualKeyCode::Numpad3 |
-            VirtualKeyCode::M => try_move_player(1, 1, &mut gs.ecs),
+            VirtualKeyCode::M => return try_move_player(1, 1, &mut gs.ecs),
 
             VirtualKeyCode::Numpad1 |
-            VirtualKeyCode::N => try_move_player(-1, 1, &mut gs.ecs),
+            VirtualKeyCode::N => return try_move_player(-1, 1, &mut gs.ecs),
 
             // Game actions
             VirtualKeyCode::G => get_item(&mut gs.ecs),
@@ -180,12 +547,23 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
                     return RunState::NextLevel;
                 }
             }
+            VirtualKeyCode::LBracket => {
+                if try_previous_level(&mut gs.ecs) {
+                    return RunState::PreviousLevel;
+                }
+            }
             VirtualKeyCode::R => return RunState::ShowRemoveItem,
+            VirtualKeyCode::C => return RunState::ShowCharacterSheet,
+            VirtualKeyCode::E => return RunState::ShowEquipment,
+            VirtualKeyCode::F => return fire_weapon(&mut gs.ecs),
+            VirtualKeyCode::O => return open_container(&mut gs.ecs),
+            VirtualKeyCode::S => return search(&mut gs.ecs),
+            VirtualKeyCode::X => return disarm_trap(&mut gs.ecs),
 
             // Skip turn
             VirtualKeyCode::Numpad5 |
             VirtualKeyCode::Period |
-            VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
+            VirtualKeyCode::Space => return RunState::PlayerTurn,
 
             // Meta actions
             VirtualKeyCode::Escape => return RunState::SaveGame,
@@ -0,0 +1,17 @@
+use serde::{ Serialize, Deserialize };
+use std::collections::{ HashMap, HashSet };
+use super::{ DamageSource, identification::Appearance };
+
+// Things that must hold for the whole run rather than a single level,
+// and the statistics shown when it ends
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct RunProgress {
+    pub uniques_spawned : Vec<String>,
+    pub deepest_depth : i32,
+    pub monsters_killed : i32,
+    pub death : Option<DamageSource>,
+
+    // Magic items look the same within a run until the player learns them
+    pub appearances : HashMap<String, Appearance>,
+    pub identified : HashSet<String>
+}
@@ -31,16 +31,19 @@ impl RandomTable {
     }
 
     pub fn roll(&self, rng : &mut RandomNumberGenerator) -> String {
-        // Partitions the total weight into chunks for each item;
-        // Depending on which chunk the roll falls into, returns the corresponding item
-        // Total=100(1:50,2:25,3:25) -> roll between 1 and 99 -> if 1-49: return item 1;
-        // if 50-74: return item 2; if 74-99: return item 3
-
         if self.total_weight == 0 { return "None".to_string(); }
-        let mut roll = rng.roll_dice(1, self.total_weight) - 1;
+        let roll = rng.roll_dice(1, self.total_weight) - 1;
+        self.pick(roll)
+    }
+
+    /// Partitions the total weight into chunks for each entry and returns the
+    /// one the roll falls into. Every entry gets exactly its weight, the first
+    /// included: with 50/25/25, rolls 0-49 give the first entry, 50-74 the
+    /// second and 75-99 the third.
+    fn pick(&self, mut roll : i32) -> String {
         let mut index : usize = 0;
 
-        while roll > 0 {
+        while index < self.entries.len() {
             if roll < self.entries[index].weight {
                 return self.entries[index].name.clone();
             }
@@ -49,6 +52,57 @@ impl RandomTable {
             index += 1;
         }
 
-        return "None".to_string();
+        "None".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn counts(table : &RandomTable, rolls : usize) -> HashMap<String, usize> {
+        let mut rng = RandomNumberGenerator::seeded(42);
+        let mut counts = HashMap::new();
+        for _roll in 0 .. rolls {
+            *counts.entry(table.roll(&mut rng)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn an_empty_table_rolls_none() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert_eq!(RandomTable::new().roll(&mut rng), "None");
+    }
+
+    #[test]
+    fn a_single_entry_always_comes_up() {
+        let counts = counts(&RandomTable::new().add("Gold", 1), 100);
+        assert_eq!(counts.get("Gold"), Some(&100));
+    }
+
+    #[test]
+    fn entries_without_weight_are_never_rolled() {
+        let table = RandomTable::new().add("Orc", 2).add("Drake", 0).add("Wyrm", -3);
+        let counts = counts(&table, 500);
+        assert_eq!(counts.get("Orc"), Some(&500));
+    }
+
+    #[test]
+    fn every_entry_covers_exactly_its_weight() {
+        let table = RandomTable::new().add("Goblin", 3).add("Orc", 1).add("Drake", 2);
+        let picks : Vec<String> = (0 .. 6).map(|roll| table.pick(roll)).collect();
+        assert_eq!(picks, ["Goblin", "Goblin", "Goblin", "Orc", "Drake", "Drake"]);
+    }
+
+    #[test]
+    fn rolls_follow_the_weights() {
+        let table = RandomTable::new().add("Common", 3).add("Rare", 1);
+        let counts = counts(&table, 4000);
+        let common = *counts.get("Common").unwrap() as f32;
+        let rare = *counts.get("Rare").unwrap() as f32;
+        assert!(!counts.contains_key("None"));
+        assert!((common / rare - 3.0).abs() < 0.5);
     }
 }
@@ -0,0 +1,222 @@
+use rltk::{ Point, RandomNumberGenerator, RGB };
+use specs::prelude::*;
+use super::{
+    Ammunition,
+    armor_class,
+    Attributes,
+    CombatStats,
+    DamageType,
+    DefenseBonus,
+    EffectQueue,
+    EffectType,
+    Encumbrance,
+    Equipped,
+    GameLog,
+    InBackpack,
+    Map,
+    map::TileType,
+    Name,
+    Position,
+    practise_skill,
+    RangedWeapon,
+    Skill,
+    skill_check,
+    Skills,
+    Stackable,
+    Targets,
+    WantsToShoot
+};
+
+/// Follow a Bresenham line from the shooter towards the target. The shot stops
+/// at the first wall or the first thing that can be hurt, which may not be the
+/// intended target. Returns the tiles travelled and whatever was hit.
+pub fn line_of_fire(
+    map : &Map,
+    combat_stats : &ReadStorage<CombatStats>,
+    start : Point,
+    end : Point
+) -> (Vec<Point>, Option<Entity>) {
+    let mut path = Vec::new();
+    for point in rltk::line2d_bresenham(start, end).iter().skip(1) {
+        if point.x < 0 || point.x >= map.width || point.y < 0 || point.y >= map.height {
+            break;
+        }
+        let idx = map.xy_idx(point.x, point.y);
+        if map.tiles[idx] == TileType::Wall {
+            break;
+        }
+        path.push(*point);
+        for entity in map.tile_content[idx].iter() {
+            if combat_stats.get(*entity).is_some() {
+                return (path, Some(*entity));
+            }
+        }
+    }
+    (path, None)
+}
+
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Map>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, RandomNumberGenerator>,
+                       WriteExpect<'a, EffectQueue>,
+                       WriteStorage<'a, WantsToShoot>,
+                       ReadStorage<'a, Name>,
+                       ReadStorage<'a, Position>,
+                       ReadStorage<'a, CombatStats>,
+                       ReadStorage<'a, Attributes>,
+                       WriteStorage<'a, Skills>,
+                       ReadStorage<'a, RangedWeapon>,
+                       ReadStorage<'a, Equipped>,
+                       ReadStorage<'a, DefenseBonus>,
+                       ReadStorage<'a, InBackpack>,
+                       ReadStorage<'a, Ammunition>,
+                       WriteStorage<'a, Stackable>,
+                       ReadStorage<'a, Encumbrance>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (
+            entities,
+            map,
+            player_entity,
+            mut gamelog,
+            mut rng,
+            mut effects,
+            mut wants_shoot,
+            names,
+            positions,
+            combat_stats,
+            attributes,
+            mut skills,
+            ranged_weapons,
+            equipped,
+            defense_bonuses,
+            backpack,
+            ammunition,
+            mut stacks,
+            encumbrance
+        ) = data;
+
+        for (entity, shot, name, pos, stats) in (&entities, &wants_shoot, &names, &positions, &combat_stats).join() {
+            if stats.hp < 1 { continue; }
+
+            // Monsters may shoot without a launcher; everyone else needs one equipped
+            let mut weapon : Option<RangedWeapon> = None;
+            let mut needs_ammo = false;
+            if let Some(innate) = ranged_weapons.get(entity) {
+                weapon = Some(innate.clone());
+            } else {
+                for (launcher, equipped_by) in (&ranged_weapons, &equipped).join() {
+                    if equipped_by.owner == entity {
+                        weapon = Some(launcher.clone());
+                        needs_ammo = true;
+                    }
+                }
+            }
+            let weapon = match weapon {
+                None => continue,
+                Some(weapon) => weapon
+            };
+
+            if needs_ammo {
+                let mut ammo_entity : Option<Entity> = None;
+                for (ammo_item, pack, ammo, stack) in (&entities, &backpack, &ammunition, &stacks).join() {
+                    if pack.owner == entity && ammo.ammo == weapon.ammo && stack.quantity > 0 {
+                        ammo_entity = Some(ammo_item);
+                    }
+                }
+                match ammo_entity {
+                    None => {
+                        if entity == *player_entity {
+                            gamelog.entries.push(format!("You have no {:?}s to shoot.", weapon.ammo));
+                        }
+                        continue;
+                    }
+                    Some(ammo_entity) => {
+                        let stack = stacks.get_mut(ammo_entity).unwrap();
+                        stack.quantity -= 1;
+                        if stack.quantity < 1 {
+                            entities.delete(ammo_entity).expect("Unable to delete ammunition");
+                        }
+                    }
+                }
+            }
+
+            let target_pos = match positions.get(shot.target) {
+                None => continue,
+                Some(target_pos) => Point::new(target_pos.x, target_pos.y)
+            };
+            let (path, hit) = line_of_fire(&map, &combat_stats, Point::new(pos.x, pos.y), target_pos);
+            effects.add(
+                Some(entity),
+                EffectType::Particle{
+                    glyph : rltk::to_cp437('*'),
+                    fg : RGB::named(rltk::CYAN),
+                    bg : RGB::named(rltk::BLACK),
+                    lifespan : 150.0
+                },
+                Targets::Tiles{ tiles : path.iter().map(|p| map.xy_idx(p.x, p.y) as i32).collect() }
+            );
+            let victim = match hit {
+                None => {
+                    gamelog.entries.push(format!("{}'s shot hits nothing.", &name.name));
+                    continue;
+                }
+                Some(victim) => victim
+            };
+            let victim_name = &names.get(victim).unwrap().name;
+            let victim_stats = combat_stats.get(victim).unwrap();
+
+            let mut target_ac = armor_class(10);
+            if let Some(attr) = attributes.get(victim) {
+                target_ac = armor_class(attr.quickness.total());
+            }
+            if let Some(victim_skills) = skills.get(victim) {
+                target_ac += victim_skills.rank(Skill::Defense);
+            }
+            // Heavy loads make it harder both to aim and to dodge
+            if let Some(burden) = encumbrance.get(victim) {
+                target_ac -= burden.state.combat_penalty();
+            }
+            if let Some(burden) = encumbrance.get(entity) {
+                target_ac += burden.state.combat_penalty();
+            }
+
+            let hit = skill_check(&mut rng, skills.get(entity), attributes.get(entity), Skill::Ranged, target_ac);
+
+            // Whoever succeeded gets better at it
+            let (practised_by, practised) = if hit { (entity, Skill::Ranged) } else { (victim, Skill::Defense) };
+            if let Some(practiser_skills) = skills.get_mut(practised_by) {
+                if practise_skill(practiser_skills, practised) && practised_by == *player_entity {
+                    gamelog.entries.push(format!(
+                        "Your {:?} skill improves to {}.",
+                        practised,
+                        practiser_skills.rank(practised)
+                    ));
+                }
+            }
+
+            if !hit {
+                gamelog.entries.push(format!("{} shoots at {}, but misses.", &name.name, victim_name));
+                continue;
+            }
+
+            let mut defensive_bonus = 0;
+            for (defense_bonus, equipped_by) in (&defense_bonuses, &equipped).join() {
+                if equipped_by.owner == victim {
+                    defensive_bonus += defense_bonus.defense;
+                }
+            }
+            let damage = i32::max(1, weapon.damage - (victim_stats.defense + defensive_bonus));
+            gamelog.entries.push(format!("{} shoots {} for {} hp.", &name.name, victim_name, damage));
+            effects.add(Some(entity), EffectType::Damage{ amount : damage, damage_type : DamageType::Physical }, Targets::Single{ target : victim });
+        }
+
+        wants_shoot.clear();
+    }
+}
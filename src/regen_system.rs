@@ -0,0 +1,50 @@
+use specs::prelude::*;
+use super::{
+    Attributes,
+    CombatStats,
+    GameClock,
+    HungerClock,
+    HungerState,
+    regen_interval,
+    RunState,
+    StatusEffects,
+    StatusKind
+};
+
+/// Natural healing for anything with a body, player and monsters alike.
+/// Fitness sets the pace; poison and starvation stop it and hunger slows it.
+pub struct RegenSystem {}
+
+impl<'a> System<'a> for RegenSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, RunState>,
+                       ReadExpect<'a, GameClock>,
+                       WriteStorage<'a, CombatStats>,
+                       ReadStorage<'a, Attributes>,
+                       ReadStorage<'a, StatusEffects>,
+                       ReadStorage<'a, HungerClock>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, runstate, clock, mut combat_stats, attributes, statuses, hunger_clocks) = data;
+
+        // Healing follows the passing of rounds, not what the player presses
+        if *runstate != RunState::MonsterTurn { return; }
+
+        for (entity, stats, attributes) in (&entities, &mut combat_stats, &attributes).join() {
+            if stats.hp < 1 || stats.hp >= stats.max_hp { continue; }
+            if statuses.get(entity).map(|s| s.has(StatusKind::Poison)).unwrap_or(false) { continue; }
+
+            let mut interval = regen_interval(attributes.fitness.total());
+            match hunger_clocks.get(entity).map(|h| h.state) {
+                Some(HungerState::Starving) => continue,
+                Some(HungerState::Hungry) => interval *= 2,
+                _ => {}
+            }
+
+            if clock.turn % interval == 0 {
+                stats.hp += 1;
+            }
+        }
+    }
+}
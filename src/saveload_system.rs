@@ -29,9 +29,11 @@ macro_rules! serialize_individually {
 pub fn save_game(ecs : &mut World) {
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let clockcopy = ecs.get_mut::<super::gameclock::GameClock>().unwrap().clone();
+    let progresscopy = ecs.get_mut::<super::progress::RunProgress>().unwrap().clone();
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper{ map : mapcopy })
+        .with(SerializationHelper{ map : mapcopy, clock : clockcopy, progress : progresscopy })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -44,10 +46,17 @@ pub fn save_game(ecs : &mut World) {
         serialize_individually!(ecs, serializer, data, Position, Renderable, Player,
                                 Viewshed, Monster, Name, BlocksTile, CombatStats, SufferDamage,
                                 WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
-                                AreaOfEffect, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem,
+                                AreaOfEffect, InflictsStatus, ProvidesHealing, InBackpack, WantsToPickupItem,
                                 WantsToUseItem, WantsToDropItem, SerializationHelper,
                                 Equippable, Equipped, MeleePowerBonus, DefenseBonus,
-                                WantsToRemoveItem);
+                                WantsToRemoveItem, Attributes, Skills, RangedWeapon,
+                                Ammunition, WantsToShoot, TwoHanded, StatusEffects,
+                                Teleports, Resistances, MonsterMind, Faction,
+                                WantsToMove, Summons, ConeOfEffect, Ability, GoalItem,
+                                ProvidesFood, HungerClock, MagicItem, IdentifiesItems, Cursed, RemovesCurse,
+                                Stackable, Weight, Value, Gold, Purse, Vendor,
+                                LootTable, Container, Locked, Trapped, Hidden, EntryTrigger, Trap,
+                                RaisesAlarm, KilledBy);
     }
 
     // Clean up
@@ -98,10 +107,17 @@ pub fn load_game(ecs : &mut World) {
         deserialize_individually!(ecs, deserializer, data, Position, Renderable, Player, Viewshed,
                                   Monster, Name, BlocksTile, CombatStats, SufferDamage, WantsToMelee,
                                   Item, Consumable, Ranged, InflictsDamage, AreaOfEffect,
-                                  Confusion, ProvidesHealing, InBackpack, WantsToPickupItem,
+                                  InflictsStatus, ProvidesHealing, InBackpack, WantsToPickupItem,
                                   WantsToUseItem, WantsToDropItem, SerializationHelper,
                                   Equippable, Equipped, MeleePowerBonus, DefenseBonus,
-                                  WantsToRemoveItem);
+                                  WantsToRemoveItem, Attributes, Skills, RangedWeapon,
+                                  Ammunition, WantsToShoot, TwoHanded, StatusEffects,
+                                  Teleports, Resistances, MonsterMind, Faction,
+                                  WantsToMove, Summons, ConeOfEffect, Ability, GoalItem,
+                                  ProvidesFood, HungerClock, MagicItem, IdentifiesItems, Cursed, RemovesCurse,
+                                  Stackable, Weight, Value, Gold, Purse, Vendor,
+                                  LootTable, Container, Locked, Trapped, Hidden, EntryTrigger, Trap,
+                                  RaisesAlarm, KilledBy);
     }
 
     let mut deleteme : Option<Entity> = None;
@@ -115,6 +131,10 @@ pub fn load_game(ecs : &mut World) {
             let mut worldmap = ecs.write_resource::<super::map::Map>();
             *worldmap = h.map.clone();
             worldmap.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
+            let mut clock = ecs.write_resource::<super::gameclock::GameClock>();
+            *clock = h.clock.clone();
+            let mut progress = ecs.write_resource::<super::progress::RunProgress>();
+            *progress = h.progress.clone();
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
@@ -3,34 +3,91 @@ use specs::prelude::*;
 use specs::saveload::{ MarkedBuilder, SimpleMarker };
 use std::collections::HashMap;
 use super::{
+    Ability,
+    AmmoType,
+    Ammunition,
+    arena,
     AreaOfEffect,
+    Attributes,
     BlocksTile,
+    Container,
     CombatStats,
-    Confusion,
+    ConeOfEffect,
     Consumable,
+    Cursed,
+    DamageType,
     DefenseBonus,
+    EntryTrigger,
     EquipmentSlot,
     Equippable,
+    Faction,
+    FactionKind,
+    GoalItem,
+    Gold,
+    Hidden,
+    hunger_system::WELL_FED_DURATION,
+    HungerClock,
+    HungerState,
+    identification::{ disguise, MagicKind },
+    IdentifiesItems,
+    InBackpack,
     InflictsDamage,
+    InflictsStatus,
+    inventory_system::give_item,
     Item,
+    Locked,
+    LootTable,
+    Map,
     map::MAPWIDTH,
     MeleePowerBonus,
+    npc_hp_pool,
+    player_hp_at_level,
     Monster,
+    MonsterMind,
     Name,
     Player,
     Position,
+    ProvidesFood,
     ProvidesHealing,
+    progress::RunProgress,
+    Purse,
+    RaisesAlarm,
     RandomTable,
     Ranged,
+    RangedWeapon,
     Rect,
+    RemovesCurse,
     Renderable,
+    Resistances,
     SerializeMe,
-    Viewshed
+    Skill,
+    Skills,
+    Stackable,
+    StatusKind,
+    Summons,
+    Teleports,
+    Trap,
+    Trapped,
+    TwoHanded,
+    Value,
+    Vendor,
+    Viewshed,
+    Weight
 };
 
 const MAX_SPAWNS : i32 = 4;
 
+/// Named monsters that appear once per run, somewhere on their own level.
+/// Bosses are placed by their arena instead.
+const UNIQUES : [(&str, i32); 2] = [
+    ("Grizzle the Old Wolf", 2),
+    ("Mother of Webs", 7)
+];
+
 pub fn player(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let attributes = Attributes::new(11, 11, 11, 11);
+    let max_hp = player_hp_at_level(attributes.fitness.total(), 1);
+
     ecs
         .create_entity()
         .with(Position { x, y })
@@ -41,59 +98,392 @@ pub fn player(ecs : &mut World, x : i32, y : i32) -> Entity {
             render_order : 0
         })
         .with(Player{})
+        .with(Faction{ faction : FactionKind::Player })
         .with(Name { name : "Player".to_string() })
         .with(Viewshed {
             visible_tiles : Vec::new(),
             range : 8,
             dirty : true
         })
-        .with(CombatStats { max_hp : 30, hp : 30, defense : 2, power : 5 })
+        .with(CombatStats { max_hp, hp : max_hp, defense : 2, power : 5, level : 1, xp : 0 })
+        .with(attributes)
+        .with(Skills::new(&[(Skill::Melee, 1), (Skill::Ranged, 1), (Skill::Defense, 1), (Skill::Magic, 1), (Skill::Perception, 1)]))
+        .with(HungerClock{ state : HungerState::WellFed, duration : WELL_FED_DURATION })
+        .with(Purse{ gold : 0 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
 
-fn monster(
-    ecs : &mut World,
-    x : i32,
-    y : i32,
-    glyph_char : char,
-    name : &str,
-    max_hp : i32,
+/// The stat block every monster is built from
+struct MonsterTemplate<'a> {
+    glyph : char,
+    name : &'a str,
+    level : i32,
+    attributes : Attributes,
+    skills : Skills,
     defense : i32,
-    power : i32
-) {
-    ecs
+    power : i32,
+    faction : FactionKind,
+    loot : Option<&'a str>
+}
+
+fn monster(ecs : &mut World, x : i32, y : i32, template : MonsterTemplate) -> Entity {
+    let max_hp = npc_hp_pool(template.attributes.fitness.total(), template.level);
+
+    let monster = ecs
         .create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph : rltk::to_cp437(glyph_char),
+            glyph : rltk::to_cp437(template.glyph),
             fg : RGB::named(rltk::RED),
             bg : RGB::named(rltk::BLACK),
             render_order : 1
         })
         .with(Monster {})
-        .with(Name { name : format!("{}", &name.to_string()) })
+        .with(MonsterMind::default())
+        .with(Name { name : template.name.to_string() })
         .with(Viewshed {
             visible_tiles : Vec::new(),
             range : 8,
             dirty : true
         })
         .with(BlocksTile {})
-        .with(CombatStats { max_hp, hp : max_hp, defense, power })
+        .with(CombatStats { max_hp, hp : max_hp, defense : template.defense, power : template.power, level : template.level, xp : 0 })
+        .with(template.attributes)
+        .with(template.skills)
+        .with(Faction{ faction : template.faction })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    if let Some(table) = template.loot {
+        ecs.write_storage::<LootTable>()
+            .insert(monster, LootTable{ table : table.to_string() })
+            .expect("Unable to insert loot table");
+    }
+    monster
+}
+
+/// Anything not hostile to the player is drawn in a different colour
+fn set_colour(ecs : &mut World, entity : Entity, fg : RGB) {
+    if let Some(renderable) = ecs.write_storage::<Renderable>().get_mut(entity) {
+        renderable.fg = fg;
+    }
+}
+
+fn orc(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let orc = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'o',
+        name : "Orc",
+        level : 2,
+        attributes : Attributes::new(14, 14, 9, 8),
+        skills : Skills::new(&[(Skill::Melee, 2), (Skill::Defense, 1)]),
+        defense : 4,
+        power : 6,
+        faction : FactionKind::Orcs,
+        loot : Some("Orcs")
+    });
+    ecs.write_storage::<Resistances>()
+        .insert(orc, Resistances::new(&[(DamageType::Poison, 50), (DamageType::Cold, -50)]))
+        .expect("Unable to insert resistances");
+    orc
+}
+
+fn goblin_archer(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let archer = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'a',
+        name : "Goblin Archer",
+        level : 2,
+        attributes : Attributes::new(9, 10, 14, 8),
+        skills : Skills::new(&[(Skill::Melee, 1), (Skill::Ranged, 2), (Skill::Defense, 1), (Skill::Stealth, 2)]),
+        defense : 1,
+        power : 2,
+        faction : FactionKind::Goblins,
+        loot : Some("Goblin Archers")
+    });
+    ecs.write_storage::<RangedWeapon>()
+        .insert(archer, RangedWeapon{ range : 6, damage : 4, ammo : AmmoType::Arrow })
+        .expect("Unable to insert ranged weapon");
+    archer
+}
+
+fn goblin(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let goblin = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'g',
+        name : "Goblin",
+        level : 1,
+        attributes : Attributes::new(9, 10, 12, 8),
+        skills : Skills::new(&[(Skill::Melee, 1), (Skill::Defense, 1), (Skill::Stealth, 2)]),
+        defense : 1,
+        power : 4,
+        faction : FactionKind::Goblins,
+        loot : Some("Goblins")
+    });
+    ecs.write_storage::<Resistances>()
+        .insert(goblin, Resistances::new(&[(DamageType::Fire, -50)]))
+        .expect("Unable to insert resistances");
+    goblin
 }
 
-fn orc(ecs : &mut World, x : i32, y : i32) {
-    monster(ecs, x, y, 'o', "Orc", 30, 4, 8);
+fn deer(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let deer = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'd',
+        name : "Deer",
+        level : 1,
+        attributes : Attributes::new(8, 10, 14, 6),
+        skills : Skills::new(&[(Skill::Defense, 1)]),
+        defense : 0,
+        power : 2,
+        faction : FactionKind::Wildlife,
+        loot : Some("Game")
+    });
+    set_colour(ecs, deer, RGB::named(rltk::YELLOW));
+    deer
 }
 
-fn goblin(ecs : &mut World, x : i32, y : i32) {
-    monster(ecs, x, y, 'g', "Goblin", 16, 1, 4);
+fn lost_hound(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let hound = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'h',
+        name : "Lost Hound",
+        level : 2,
+        attributes : Attributes::new(12, 12, 14, 4),
+        skills : Skills::new(&[(Skill::Melee, 2), (Skill::Defense, 1)]),
+        defense : 1,
+        power : 4,
+        faction : FactionKind::Companions,
+        loot : None
+    });
+    set_colour(ecs, hound, RGB::named(rltk::GREEN));
+    hound
 }
 
-fn health_potion(ecs : &mut World, x : i32, y : i32) {
+/// Abilities are entities of their own, built from the same components as items
+fn ability<'a>(ecs : &'a mut World, owner : Entity, name : &str, range : i32, cooldown : i32) -> EntityBuilder<'a> {
     ecs.create_entity()
+        .with(Name{ name : name.to_string() })
+        .with(Ability{ owner, range, cooldown, ready_in : 0 })
+        .marked::<SimpleMarker<SerializeMe>>()
+}
+
+fn goblin_shaman(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let shaman = monster(ecs, x, y, MonsterTemplate {
+        glyph : 's',
+        name : "Goblin Shaman",
+        level : 3,
+        attributes : Attributes::new(8, 10, 12, 14),
+        skills : Skills::new(&[(Skill::Melee, 1), (Skill::Defense, 1), (Skill::Magic, 2)]),
+        defense : 1,
+        power : 3,
+        faction : FactionKind::Goblins,
+        loot : Some("Goblin Shamans")
+    });
+    ability(ecs, shaman, "Mend", 0, 8)
+        .with(ProvidesHealing{ heal_amount : 8 })
+        .build();
+    ability(ecs, shaman, "Call Goblins", 6, 20)
+        .with(Summons{ name : "Goblin".to_string(), count : 2 })
+        .build();
+    ability(ecs, shaman, "Blink", 1, 10)
+        .with(Teleports{ range : Some(5) })
+        .build();
+    shaman
+}
+
+fn cave_spider(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let spider = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'S',
+        name : "Cave Spider",
+        level : 2,
+        attributes : Attributes::new(8, 8, 14, 2),
+        skills : Skills::new(&[(Skill::Melee, 2), (Skill::Defense, 2)]),
+        defense : 1,
+        power : 3,
+        faction : FactionKind::Beasts,
+        loot : Some("Spiders")
+    });
+    ecs.write_storage::<Resistances>()
+        .insert(spider, Resistances::new(&[(DamageType::Poison, 100)]))
+        .expect("Unable to insert resistances");
+    ability(ecs, spider, "Venom Spit", 5, 4)
+        .with(Ranged{ range : 5 })
+        .with(InflictsDamage{ damage : 3, damage_type : DamageType::Poison })
+        .with(InflictsStatus{ kind : StatusKind::Poison, turns : 3, magnitude : 1 })
+        .build();
+    spider
+}
+
+fn young_drake(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let drake = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'D',
+        name : "Young Drake",
+        level : 4,
+        attributes : Attributes::new(16, 16, 10, 10),
+        skills : Skills::new(&[(Skill::Melee, 3), (Skill::Defense, 2)]),
+        defense : 4,
+        power : 8,
+        faction : FactionKind::Beasts,
+        loot : Some("Drakes")
+    });
+    ecs.write_storage::<Resistances>()
+        .insert(drake, Resistances::new(&[(DamageType::Fire, 100), (DamageType::Cold, -50)]))
+        .expect("Unable to insert resistances");
+    ability(ecs, drake, "Fire Breath", 4, 6)
+        .with(Ranged{ range : 4 })
+        .with(ConeOfEffect{})
+        .with(InflictsDamage{ damage : 10, damage_type : DamageType::Fire })
+        .build();
+    drake
+}
+
+/// Build an item straight into a monster's backpack, so it drops when they die
+fn carried_item<'a>(
+    ecs : &'a mut World,
+    owner : Entity,
+    name : &str,
+    glyph_char : char,
+    fg : RGB
+) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Renderable {
+            glyph : rltk::to_cp437(glyph_char),
+            fg,
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(InBackpack{ owner })
+        .marked::<SimpleMarker<SerializeMe>>()
+}
+
+fn grizzle(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let wolf = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'W',
+        name : "Grizzle the Old Wolf",
+        level : 4,
+        attributes : Attributes::new(15, 15, 14, 6),
+        skills : Skills::new(&[(Skill::Melee, 3), (Skill::Defense, 2)]),
+        defense : 2,
+        power : 7,
+        faction : FactionKind::Beasts,
+        loot : None
+    });
+    set_colour(ecs, wolf, RGB::named(rltk::MAGENTA));
+    carried_item(ecs, wolf, "Grizzle's Pelt", '[', RGB::named(rltk::GREY))
+        .with(Weight{ lbs : 8.0 })
+        .with(Value{ gold : 80 })
+        .with(Equippable{ slot : EquipmentSlot::Body })
+        .with(DefenseBonus{ defense : 2 })
+        .with(Resistances::new(&[(DamageType::Cold, 30)]))
+        .build();
+    wolf
+}
+
+fn goblin_king(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let king = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'G',
+        name : "Skarrg the Goblin King",
+        level : 6,
+        attributes : Attributes::new(14, 14, 12, 12),
+        skills : Skills::new(&[(Skill::Melee, 3), (Skill::Defense, 2), (Skill::Magic, 2)]),
+        defense : 4,
+        power : 7,
+        faction : FactionKind::Goblins,
+        loot : None
+    });
+    set_colour(ecs, king, RGB::named(rltk::MAGENTA));
+    ability(ecs, king, "Rally", 6, 15)
+        .with(Summons{ name : "Goblin".to_string(), count : 3 })
+        .build();
+    ability(ecs, king, "Mend", 0, 10)
+        .with(ProvidesHealing{ heal_amount : 12 })
+        .build();
+    carried_item(ecs, king, "Crown of the Goblin King", '^', RGB::named(rltk::GOLD))
+        .with(Weight{ lbs : 2.0 })
+        .with(Value{ gold : 150 })
+        .with(Equippable{ slot : EquipmentSlot::Head })
+        .with(DefenseBonus{ defense : 2 })
+        .with(MeleePowerBonus{ power : 2 })
+        .build();
+    carried_item(ecs, king, "Gold", '$', RGB::named(rltk::GOLD))
+        .with(Gold{ amount : 100 })
+        .build();
+    king
+}
+
+fn mother_of_webs(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let spider = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'S',
+        name : "Mother of Webs",
+        level : 6,
+        attributes : Attributes::new(12, 14, 16, 8),
+        skills : Skills::new(&[(Skill::Melee, 3), (Skill::Defense, 3)]),
+        defense : 3,
+        power : 6,
+        faction : FactionKind::Beasts,
+        loot : None
+    });
+    set_colour(ecs, spider, RGB::named(rltk::MAGENTA));
+    ecs.write_storage::<Resistances>()
+        .insert(spider, Resistances::new(&[(DamageType::Poison, 100)]))
+        .expect("Unable to insert resistances");
+    ability(ecs, spider, "Venom Spit", 6, 3)
+        .with(Ranged{ range : 6 })
+        .with(InflictsDamage{ damage : 5, damage_type : DamageType::Poison })
+        .with(InflictsStatus{ kind : StatusKind::Poison, turns : 5, magnitude : 2 })
+        .build();
+    ability(ecs, spider, "Brood", 6, 15)
+        .with(Summons{ name : "Cave Spider".to_string(), count : 2 })
+        .build();
+    carried_item(ecs, spider, "Amulet of the Brood", '"', RGB::named(rltk::DARKGREEN))
+        .with(Weight{ lbs : 0.2 })
+        .with(Value{ gold : 120 })
+        .with(Equippable{ slot : EquipmentSlot::Amulet })
+        .with(DefenseBonus{ defense : 1 })
+        .with(Resistances::new(&[(DamageType::Poison, 50)]))
+        .build();
+    spider
+}
+
+fn ashen_wyrm(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let wyrm = monster(ecs, x, y, MonsterTemplate {
+        glyph : 'D',
+        name : "Vexarith the Ashen Wyrm",
+        level : 10,
+        attributes : Attributes::new(20, 20, 12, 16),
+        skills : Skills::new(&[(Skill::Melee, 5), (Skill::Defense, 4), (Skill::Magic, 3)]),
+        defense : 6,
+        power : 12,
+        faction : FactionKind::Beasts,
+        loot : None
+    });
+    set_colour(ecs, wyrm, RGB::named(rltk::MAGENTA));
+    ecs.write_storage::<Resistances>()
+        .insert(wyrm, Resistances::new(&[(DamageType::Fire, 100), (DamageType::Cold, -25)]))
+        .expect("Unable to insert resistances");
+    ability(ecs, wyrm, "Ashen Breath", 6, 5)
+        .with(Ranged{ range : 6 })
+        .with(ConeOfEffect{})
+        .with(InflictsDamage{ damage : 20, damage_type : DamageType::Fire })
+        .build();
+    carried_item(ecs, wyrm, "Wyrmscale Mail", '[', RGB::named(rltk::ORANGE))
+        .with(Weight{ lbs : 15.0 })
+        .with(Value{ gold : 300 })
+        .with(Equippable{ slot : EquipmentSlot::Body })
+        .with(DefenseBonus{ defense : 5 })
+        .with(Resistances::new(&[(DamageType::Fire, 50)]))
+        .build();
+    carried_item(ecs, wyrm, "Amulet of the Ashen Deep", '"', RGB::named(rltk::GOLD))
+        .with(Weight{ lbs : 0.2 })
+        .with(Value{ gold : 1000 })
+        .with(GoalItem{})
+        .with(Equippable{ slot : EquipmentSlot::Amulet })
+        .with(DefenseBonus{ defense : 3 })
+        .with(Resistances::new(&[(DamageType::Fire, 25)]))
+        .build();
+    wyrm
+}
+
+fn health_potion(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph : rltk::to_cp437('¡'),
@@ -103,14 +493,39 @@ fn health_potion(ecs : &mut World, x : i32, y : i32) {
         })
         .with(Name{ name : "Health Potion".to_string() })
         .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 30 })
         .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
         .with(ProvidesHealing{ heal_amount : 8 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    disguise(ecs, item, MagicKind::Potion);
+    item
 }
 
-fn magic_missile_scroll(ecs : &mut World, x : i32, y : i32) {
+fn rations(ecs : &mut World, x : i32, y : i32) -> Entity {
     ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('%'),
+            fg : RGB::named(rltk::GREEN),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : "Rations".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 1.0 })
+        .with(Value{ gold : 5 })
+        .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
+        .with(ProvidesFood{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn magic_missile_scroll(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph : rltk::to_cp437(')'),
@@ -120,15 +535,20 @@ fn magic_missile_scroll(ecs : &mut World, x : i32, y : i32) {
         })
         .with(Name{ name : "Magic Missile Scroll".to_string() })
         .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 25 })
         .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
         .with(Ranged{ range : 6 })
-        .with(InflictsDamage{ damage : 8 })
+        .with(InflictsDamage{ damage : 8, damage_type : DamageType::Physical })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    disguise(ecs, item, MagicKind::Scroll);
+    item
 }
 
-fn fireball_scroll(ecs : &mut World, x : i32, y : i32) {
-    ecs.create_entity()
+fn fireball_scroll(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
         .with(Position{ x, y })
         .with(Renderable{
             glyph : rltk::to_cp437(')'),
@@ -138,16 +558,44 @@ fn fireball_scroll(ecs : &mut World, x : i32, y : i32) {
         })
         .with(Name{ name : "Fireball Scroll".to_string() })
         .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 60 })
         .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
         .with(Ranged{ range : 6 })
-        .with(InflictsDamage{ damage : 20 })
+        .with(InflictsDamage{ damage : 20, damage_type : DamageType::Fire })
         .with(AreaOfEffect { radius: 3 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    disguise(ecs, item, MagicKind::Scroll);
+    item
 }
 
-fn confusion_scroll(ecs : &mut World, x : i32, y : i32) {
-    ecs.create_entity()
+fn frost_scroll(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph : rltk::to_cp437(')'),
+            fg : RGB::named(rltk::LIGHTBLUE),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : "Frost Scroll".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 50 })
+        .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
+        .with(Ranged{ range : 6 })
+        .with(InflictsDamage{ damage : 12, damage_type : DamageType::Cold })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    disguise(ecs, item, MagicKind::Scroll);
+    item
+}
+
+fn confusion_scroll(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
         .with(Position{ x, y })
         .with(Renderable{
             glyph : rltk::to_cp437(')'),
@@ -157,15 +605,123 @@ fn confusion_scroll(ecs : &mut World, x : i32, y : i32) {
         })
         .with(Name{ name : "Confusion Scroll".to_string() })
         .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 35 })
         .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
         .with(Ranged{ range : 6})
-        .with(Confusion{ turns: 4})
+        .with(InflictsStatus{ kind : StatusKind::Confusion, turns : 4, magnitude : 0 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    disguise(ecs, item, MagicKind::Scroll);
+    item
 }
 
-fn dagger(ecs : &mut World, x : i32, y : i32) {
-    ecs.create_entity()
+fn teleport_scroll(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph : rltk::to_cp437(')'),
+            fg : RGB::named(rltk::MAGENTA),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : "Teleport Scroll".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 40 })
+        .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
+        .with(Teleports{ range : None })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    disguise(ecs, item, MagicKind::Scroll);
+    item
+}
+
+fn identify_scroll(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph : rltk::to_cp437(')'),
+            fg : RGB::named(rltk::WHITE),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : "Identify Scroll".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 30 })
+        .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
+        .with(IdentifiesItems{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    disguise(ecs, item, MagicKind::Scroll);
+    item
+}
+
+fn status_scroll(
+    ecs : &mut World,
+    x : i32,
+    y : i32,
+    name : &str,
+    fg : RGB,
+    status : InflictsStatus
+) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph : rltk::to_cp437(')'),
+            fg,
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 40 })
+        .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
+        .with(Ranged{ range : 6 })
+        .with(status)
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    disguise(ecs, item, MagicKind::Scroll);
+    item
+}
+
+fn status_potion(
+    ecs : &mut World,
+    x : i32,
+    y : i32,
+    name : &str,
+    fg : RGB,
+    status : InflictsStatus
+) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('¡'),
+            fg,
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 35 })
+        .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
+        .with(status)
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    disguise(ecs, item, MagicKind::Potion);
+    item
+}
+
+fn dagger(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph : rltk::to_cp437('/'),
@@ -175,14 +731,18 @@ fn dagger(ecs : &mut World, x : i32, y : i32) {
         })
         .with(Name{ name : "Dagger".to_string() })
         .with(Item{})
+        .with(Weight{ lbs : 1.0 })
+        .with(Value{ gold : 10 })
         .with(Equippable{ slot : EquipmentSlot::Melee })
         .with(MeleePowerBonus{ power : 2 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    roll_magic(ecs, item);
+    item
 }
 
-fn shield(ecs : &mut World, x : i32, y : i32) {
-    ecs.create_entity()
+fn shield(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph : rltk::to_cp437('('),
@@ -192,14 +752,18 @@ fn shield(ecs : &mut World, x : i32, y : i32) {
         })
         .with(Name{ name : "Shield".to_string() })
         .with(Item{})
+        .with(Weight{ lbs : 6.0 })
+        .with(Value{ gold : 15 })
         .with(Equippable{ slot : EquipmentSlot::Shield })
         .with(DefenseBonus{ defense : 1 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    roll_magic(ecs, item);
+    item
 }
 
-fn longsword(ecs : &mut World, x : i32, y : i32) {
-    ecs.create_entity()
+fn longsword(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph : rltk::to_cp437('/'),
@@ -209,14 +773,18 @@ fn longsword(ecs : &mut World, x : i32, y : i32) {
         })
         .with(Name{ name : "Longsword".to_string() })
         .with(Item{})
+        .with(Weight{ lbs : 3.0 })
+        .with(Value{ gold : 40 })
         .with(Equippable{ slot : EquipmentSlot::Melee })
         .with(MeleePowerBonus{ power : 4 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    roll_magic(ecs, item);
+    item
 }
 
-fn tower_shield(ecs : &mut World, x : i32, y : i32) {
-    ecs.create_entity()
+fn tower_shield(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph : rltk::to_cp437('('),
@@ -226,24 +794,702 @@ fn tower_shield(ecs : &mut World, x : i32, y : i32) {
         })
         .with(Name{ name : "Tower Shield".to_string() })
         .with(Item{})
+        .with(Weight{ lbs : 15.0 })
+        .with(Value{ gold : 60 })
         .with(Equippable{ slot : EquipmentSlot::Shield })
         .with(DefenseBonus{ defense : 3 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    roll_magic(ecs, item);
+    item
+}
+
+fn greatsword(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('/'),
+            fg : RGB::named(rltk::LIGHTSTEELBLUE),
+            bg : RGB::named(rltk::ORANGE4),
+            render_order : 2
+        })
+        .with(Name{ name : "Greatsword".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 8.0 })
+        .with(Value{ gold : 80 })
+        .with(Equippable{ slot : EquipmentSlot::Melee })
+        .with(TwoHanded{})
+        .with(MeleePowerBonus{ power : 7 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    roll_magic(ecs, item);
+    item
+}
+
+fn armor(
+    ecs : &mut World,
+    x : i32,
+    y : i32,
+    name : &str,
+    fg : RGB,
+    slot : EquipmentSlot,
+    defense : i32
+) -> Entity {
+    let glyph_char = match slot {
+        EquipmentSlot::Head => '^',
+        EquipmentSlot::Hands => '{',
+        EquipmentSlot::Feet => ']',
+        EquipmentSlot::LeftRing | EquipmentSlot::RightRing => '=',
+        EquipmentSlot::Amulet => '"',
+        _ => '['
+    };
+    // Sturdier protection is heavier, and body armor most of all
+    let lbs = match slot {
+        EquipmentSlot::Body => defense as f32 * 8.0,
+        EquipmentSlot::LeftRing | EquipmentSlot::RightRing | EquipmentSlot::Amulet => 0.2,
+        _ => defense as f32 * 1.5
+    };
+    let gold = match slot {
+        EquipmentSlot::LeftRing | EquipmentSlot::RightRing | EquipmentSlot::Amulet => defense * 60,
+        _ => defense * 20
+    };
+
+    let item = ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437(glyph_char),
+            fg,
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(Weight{ lbs })
+        .with(Value{ gold })
+        .with(Equippable{ slot })
+        .with(DefenseBonus{ defense })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    roll_magic(ecs, item);
+    item
+}
+
+fn ring_of_might(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('='),
+            fg : RGB::named(rltk::RED),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : "Ring of Might".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 0.2 })
+        .with(Value{ gold : 100 })
+        .with(Equippable{ slot : EquipmentSlot::LeftRing })
+        .with(MeleePowerBonus{ power : 2 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    roll_magic(ecs, item);
+    item
+}
+
+fn resistance_ring(
+    ecs : &mut World,
+    x : i32,
+    y : i32,
+    name : &str,
+    fg : RGB,
+    damage_type : DamageType
+) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('='),
+            fg,
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 0.2 })
+        .with(Value{ gold : 90 })
+        .with(Equippable{ slot : EquipmentSlot::LeftRing })
+        .with(Resistances::new(&[(damage_type, 50)]))
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    roll_magic(ecs, item);
+    item
+}
+
+fn ranged_weapon(
+    ecs : &mut World,
+    x : i32,
+    y : i32,
+    name : &str,
+    fg : RGB,
+    weapon : RangedWeapon,
+    lbs : f32
+) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('}'),
+            fg,
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(Weight{ lbs })
+        .with(Value{ gold : weapon.damage * 5 })
+        .with(Equippable{ slot : EquipmentSlot::Ranged })
+        .with(weapon)
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    roll_magic(ecs, item);
+    item
+}
+
+fn shortbow(ecs : &mut World, x : i32, y : i32) -> Entity {
+    ranged_weapon(ecs, x, y, "Shortbow", RGB::named(rltk::BURLYWOOD),
+        RangedWeapon{ range : 6, damage : 5, ammo : AmmoType::Arrow }, 2.0)
+}
+
+fn crossbow(ecs : &mut World, x : i32, y : i32) -> Entity {
+    ranged_weapon(ecs, x, y, "Crossbow", RGB::named(rltk::SLATEGRAY),
+        RangedWeapon{ range : 8, damage : 8, ammo : AmmoType::Bolt }, 5.0)
+}
+
+fn sling(ecs : &mut World, x : i32, y : i32) -> Entity {
+    ranged_weapon(ecs, x, y, "Sling", RGB::named(rltk::TAN),
+        RangedWeapon{ range : 4, damage : 3, ammo : AmmoType::Stone }, 0.5)
+}
+
+fn prefix_table() -> RandomTable {
+    RandomTable::new()
+        .add("Sturdy", 3)
+        .add("Vicious", 3)
+        .add("Fireproof", 2)
+        .add("Frostproof", 2)
+}
+
+fn suffix_table() -> RandomTable {
+    RandomTable::new()
+        .add("of Protection", 3)
+        .add("of Might", 3)
+        .add("of the Viper", 2)
+        .add("of Grounding", 2)
+}
+
+fn add_power(ecs : &mut World, item : Entity, amount : i32) {
+    let mut bonuses = ecs.write_storage::<MeleePowerBonus>();
+    match bonuses.get_mut(item) {
+        Some(bonus) => bonus.power += amount,
+        None => { bonuses.insert(item, MeleePowerBonus{ power : amount }).expect("Unable to insert power bonus"); }
+    }
+}
+
+fn add_defense(ecs : &mut World, item : Entity, amount : i32) {
+    let mut bonuses = ecs.write_storage::<DefenseBonus>();
+    match bonuses.get_mut(item) {
+        Some(bonus) => bonus.defense += amount,
+        None => { bonuses.insert(item, DefenseBonus{ defense : amount }).expect("Unable to insert defense bonus"); }
+    }
+}
+
+fn add_resistance(ecs : &mut World, item : Entity, damage_type : DamageType, percent : i32) {
+    let mut resistances = ecs.write_storage::<Resistances>();
+    match resistances.get_mut(item) {
+        Some(resistance) => *resistance.percent.entry(damage_type).or_insert(0) += percent,
+        None => { resistances.insert(item, Resistances::new(&[(damage_type, percent)])).expect("Unable to insert resistances"); }
+    }
+}
+
+fn apply_affix(ecs : &mut World, item : Entity, affix : &str) {
+    match affix {
+        "Sturdy" | "of Protection" => add_defense(ecs, item, 1),
+        "Vicious" | "of Might" => add_power(ecs, item, 1),
+        "Fireproof" => add_resistance(ecs, item, DamageType::Fire, 25),
+        "Frostproof" => add_resistance(ecs, item, DamageType::Cold, 25),
+        "of the Viper" => add_resistance(ecs, item, DamageType::Poison, 25),
+        "of Grounding" => add_resistance(ecs, item, DamageType::Lightning, 25),
+        _ => {}
+    }
+}
+
+/// Equipment found lying around may be enchanted or cursed, which shifts its
+/// main bonus, and may carry a magical prefix or suffix from the affix tables
+fn roll_magic(ecs : &mut World, item : Entity) {
+    let (enchantment, prefix, suffix) = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let enchantment = match rng.roll_dice(1, 100) {
+            1 ..= 10 => -rng.roll_dice(1, 3),
+            11 ..= 30 => rng.roll_dice(1, 2),
+            _ => 0
+        };
+        let prefix = if rng.roll_dice(1, 100) <= 15 { prefix_table().roll(&mut rng) } else { "None".to_string() };
+        let suffix = if rng.roll_dice(1, 100) <= 15 { suffix_table().roll(&mut rng) } else { "None".to_string() };
+        (enchantment, prefix, suffix)
+    };
+
+    // Weapons hit harder, everything else protects better
+    if enchantment != 0 {
+        if ecs.read_storage::<MeleePowerBonus>().get(item).is_some() {
+            add_power(ecs, item, enchantment);
+        } else if ecs.read_storage::<RangedWeapon>().get(item).is_some() {
+            let mut weapons = ecs.write_storage::<RangedWeapon>();
+            let weapon = weapons.get_mut(item).unwrap();
+            weapon.damage = i32::max(1, weapon.damage + enchantment);
+        } else {
+            add_defense(ecs, item, enchantment);
+        }
+    }
+    if enchantment < 0 {
+        ecs.write_storage::<Cursed>().insert(item, Cursed{}).expect("Unable to insert curse");
+    }
+    apply_affix(ecs, item, &prefix);
+    apply_affix(ecs, item, &suffix);
+
+    // Enchantments and affixes make an item worth more, curses much less
+    if let Some(value) = ecs.write_storage::<Value>().get_mut(item) {
+        let affixes = [&prefix, &suffix].iter().filter(|affix| affix.as_str() != "None").count() as i32;
+        value.gold += affixes * 30;
+        if enchantment > 0 { value.gold += enchantment * 50; }
+        if enchantment < 0 { value.gold /= 4; }
+    }
+
+    if let Some(name) = ecs.write_storage::<Name>().get_mut(item) {
+        let mut full_name = name.name.clone();
+        if prefix != "None" { full_name = format!("{} {}", prefix, full_name); }
+        if enchantment != 0 { full_name = format!("{} {:+}", full_name, enchantment); }
+        if suffix != "None" { full_name = format!("{} {}", full_name, suffix); }
+        name.name = full_name;
+    }
+}
+
+fn remove_curse_scroll(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph : rltk::to_cp437(')'),
+            fg : RGB::named(rltk::WHITE),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : "Remove Curse Scroll".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs : 0.5 })
+        .with(Value{ gold : 50 })
+        .with(Consumable{})
+        .with(Stackable{ quantity : 1 })
+        .with(RemovesCurse{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    disguise(ecs, item, MagicKind::Scroll);
+    item
+}
+
+fn ammunition(ecs : &mut World, x : i32, y : i32, name : &str, ammo : AmmoType, count : i32) -> Entity {
+    let (lbs, gold) = if ammo == AmmoType::Stone { (0.2, 1) } else { (0.1, 2) };
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('|'),
+            fg : RGB::named(rltk::BURLYWOOD),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(Weight{ lbs })
+        .with(Value{ gold })
+        .with(Ammunition{ ammo })
+        .with(Stackable{ quantity : count })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn gold(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let amount = ecs.write_resource::<RandomNumberGenerator>().roll_dice(2, 10);
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('$'),
+            fg : RGB::named(rltk::GOLD),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : "Gold".to_string() })
+        .with(Item{})
+        .with(Gold{ amount })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn corpse(ecs : &mut World, x : i32, y : i32, victim : &str) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('%'),
+            fg : RGB::named(rltk::RED),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : format!("{} Corpse", victim) })
+        .with(Item{})
+        .with(Weight{ lbs : 10.0 })
+        .with(Value{ gold : 1 })
+        .with(Consumable{})
+        .with(ProvidesFood{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// What each kind of monster may leave behind when it dies
+fn loot_table(table : &str) -> RandomTable {
+    match table {
+        "Goblins" => RandomTable::new()
+            .add("None", 6)
+            .add("Gold", 4)
+            .add("Rations", 1)
+            .add("Health Potion", 1)
+            .add("Dagger", 1),
+        "Goblin Archers" => RandomTable::new()
+            .add("None", 4)
+            .add("Gold", 3)
+            .add("Arrows", 4)
+            .add("Shortbow", 1),
+        "Goblin Shamans" => RandomTable::new()
+            .add("None", 3)
+            .add("Gold", 3)
+            .add("Magic Missile Scroll", 2)
+            .add("Confusion Scroll", 1)
+            .add("Identify Scroll", 1),
+        "Orcs" => RandomTable::new()
+            .add("None", 5)
+            .add("Gold", 4)
+            .add("Rations", 2)
+            .add("Health Potion", 1)
+            .add("Shield", 1)
+            .add("Longsword", 1),
+        "Game" => RandomTable::new()
+            .add("None", 1)
+            .add("Corpse", 3),
+        "Spiders" => RandomTable::new()
+            .add("None", 4)
+            .add("Poison Potion", 1),
+        "Drakes" => RandomTable::new()
+            .add("None", 2)
+            .add("Corpse", 2)
+            .add("Gold", 3)
+            .add("Ring of Fire Resistance", 1),
+        _ => RandomTable::new()
+    }
+}
+
+/// Roll on a dead monster's drop table and leave the result where it fell
+pub fn spawn_loot(ecs : &mut World, table : &str, victim : &str, x : i32, y : i32) {
+    let drop = loot_table(table).roll(&mut ecs.write_resource::<RandomNumberGenerator>());
+    match drop.as_str() {
+        "None" => {}
+        "Corpse" => { corpse(ecs, x, y, victim); }
+        _ => { spawn_entity(ecs, &drop, x, y); }
+    }
+}
+
+fn shopkeeper(ecs : &mut World, x : i32, y : i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('@'),
+            fg : RGB::named(rltk::GOLD),
+            bg : RGB::named(rltk::BLACK),
+            render_order : 1
+        })
+        .with(Name{ name : "Shopkeeper".to_string() })
+        .with(BlocksTile{})
+        .with(Vendor{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn container<'a>(ecs : &'a mut World, x : i32, y : i32, name : &str, glyph : char, fg : RGB) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437(glyph),
+            fg,
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Container{})
+        .marked::<SimpleMarker<SerializeMe>>()
+}
+
+fn fill_container(ecs : &mut World, container : Entity, table : RandomTable, count : i32) {
+    let contents : Vec<String> = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        (0 .. count).map(|_| table.roll(&mut rng)).filter(|name| name != "None").collect()
+    };
+    for name in contents.iter() {
+        spawn_carried(ecs, container, name);
+    }
+}
+
+fn chest_table(map_depth : i32) -> RandomTable {
+    RandomTable::new()
+        .add("Gold", 6)
+        .add("Health Potion", 4)
+        .add("Potion of Haste", 1)
+        .add("Potion of Regeneration", 1)
+        .add("Potion of Strength", 1)
+        .add("Magic Missile Scroll", 2)
+        .add("Fireball Scroll", map_depth)
+        .add("Identify Scroll", 2)
+        .add("Teleport Scroll", 1)
+        .add("Remove Curse Scroll", 1)
+        .add("Dagger", 1)
+        .add("Longsword", map_depth - 1)
+        .add("Leather Armor", 1)
+        .add("Chain Mail", map_depth - 2)
+        .add("Ring of Protection", map_depth - 1)
+        .add("Ring of Might", map_depth - 1)
+        .add("Amulet of Warding", map_depth - 2)
+}
+
+fn barrel_table() -> RandomTable {
+    RandomTable::new()
+        .add("None", 2)
+        .add("Rations", 4)
+        .add("Arrows", 2)
+        .add("Sling Stones", 2)
+        .add("Crossbow Bolts", 1)
+        .add("Gold", 1)
+}
+
+/// Chests hold the better finds, so they are often locked and sometimes trapped
+fn chest(ecs : &mut World, x : i32, y : i32, map_depth : i32) -> Entity {
+    let (lock, trap, count) = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let lock = if rng.roll_dice(1, 3) == 1 { Some(rng.roll_dice(1, 6) + 8 + map_depth) } else { None };
+        let trap = if rng.roll_dice(1, 5) == 1 { Some(rng.roll_dice(1, 2)) } else { None };
+        (lock, trap, rng.roll_dice(1, 3))
+    };
+
+    let chest = container(ecs, x, y, "Chest", '=', RGB::named(rltk::BURLYWOOD)).build();
+    if let Some(difficulty) = lock {
+        ecs.write_storage::<Locked>().insert(chest, Locked{ difficulty }).expect("Insert fail");
+    }
+    match trap {
+        Some(1) => {
+            ecs.write_storage::<Trapped>().insert(chest, Trapped{}).expect("Insert fail");
+            ecs.write_storage::<InflictsDamage>().insert(chest, InflictsDamage{ damage : 6 + map_depth, damage_type : DamageType::Fire })
+                .expect("Insert fail");
+        }
+        Some(_) => {
+            ecs.write_storage::<Trapped>().insert(chest, Trapped{}).expect("Insert fail");
+            ecs.write_storage::<InflictsDamage>().insert(chest, InflictsDamage{ damage : 2, damage_type : DamageType::Poison })
+                .expect("Insert fail");
+            ecs.write_storage::<InflictsStatus>().insert(chest, InflictsStatus{ kind : StatusKind::Poison, turns : 5, magnitude : 1 })
+                .expect("Insert fail");
+        }
+        None => {}
+    }
+    fill_container(ecs, chest, chest_table(map_depth), count);
+    chest
+}
+
+fn barrel(ecs : &mut World, x : i32, y : i32) -> Entity {
+    let count = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 2);
+    let barrel = container(ecs, x, y, "Barrel", 'o', RGB::named(rltk::SADDLEBROWN)).build();
+    fill_container(ecs, barrel, barrel_table(), count);
+    barrel
+}
+
+/// Whatever the dead were carrying stays with them, to be searched through
+pub fn remains(ecs : &mut World, x : i32, y : i32, victim : &str) -> Entity {
+    container(ecs, x, y, &format!("Remains of {}", victim), '%', RGB::named(rltk::DARKRED)).build()
+}
+
+fn trap<'a>(ecs : &'a mut World, x : i32, y : i32, name : &str, fg : RGB, map_depth : i32) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph : rltk::to_cp437('^'),
+            fg,
+            bg : RGB::named(rltk::BLACK),
+            render_order : 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Hidden{})
+        .with(EntryTrigger{})
+        .with(Trap{ difficulty : 10 + map_depth })
+        .marked::<SimpleMarker<SerializeMe>>()
+}
+
+fn spike_pit(ecs : &mut World, x : i32, y : i32, map_depth : i32) {
+    trap(ecs, x, y, "Spike Pit", RGB::named(rltk::GREY), map_depth)
+        .with(InflictsDamage{ damage : 4 + map_depth, damage_type : DamageType::Physical })
+        .build();
+}
+
+fn teleport_trap(ecs : &mut World, x : i32, y : i32, map_depth : i32) {
+    trap(ecs, x, y, "Teleport Trap", RGB::named(rltk::MAGENTA), map_depth)
+        .with(Teleports{ range : None })
+        .build();
+}
+
+fn alarm_trap(ecs : &mut World, x : i32, y : i32, map_depth : i32) {
+    trap(ecs, x, y, "Alarm Trap", RGB::named(rltk::YELLOW), map_depth)
+        .with(RaisesAlarm{ radius : 15 })
+        .with(Consumable{})
+        .build();
+}
+
+fn poison_gas_trap(ecs : &mut World, x : i32, y : i32, map_depth : i32) {
+    trap(ecs, x, y, "Poison Gas Trap", RGB::named(rltk::GREEN), map_depth)
+        .with(InflictsStatus{ kind : StatusKind::Poison, turns : 6, magnitude : 1 })
+        .with(AreaOfEffect{ radius : 2 })
+        .with(Consumable{})
+        .build();
+}
+
+fn trap_table(map_depth : i32) -> RandomTable {
+    RandomTable::new()
+        .add("None", 10)
+        .add("Spike Pit", 2 + map_depth)
+        .add("Alarm Trap", map_depth)
+        .add("Teleport Trap", map_depth - 2)
+        .add("Poison Gas Trap", map_depth - 3)
+}
+
+/// Scatter hidden traps around a room, clear of the middle where stairs go
+fn spawn_traps(ecs : &mut World, room : &Rect, map_depth : i32) {
+    let traps : Vec<(String, i32, i32)> = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let table = trap_table(map_depth);
+        let centre = room.center();
+        (0 .. 2).filter_map(|_| {
+            let name = table.roll(&mut rng);
+            let x = room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1));
+            let y = room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1));
+            if name == "None" || (x, y) == centre { None } else { Some((name, x, y)) }
+        }).collect()
+    };
+    for (name, x, y) in traps.iter() {
+        match name.as_str() {
+            "Spike Pit" => spike_pit(ecs, *x, *y, map_depth),
+            "Teleport Trap" => teleport_trap(ecs, *x, *y, map_depth),
+            "Alarm Trap" => alarm_trap(ecs, *x, *y, map_depth),
+            "Poison Gas Trap" => poison_gas_trap(ecs, *x, *y, map_depth),
+            _ => {}
+        }
+    }
 }
 
 fn room_table(map_depth : i32) -> RandomTable {
-    return RandomTable::new()
+    RandomTable::new()
         .add("Goblin", 10)
         .add("Orc", 1 + map_depth)
+        .add("Deer", 2)
+        .add("Lost Hound", 1)
+        .add("Goblin Shaman", map_depth - 1)
+        .add("Cave Spider", map_depth)
+        .add("Young Drake", map_depth - 3)
         .add("Health Potion", 7)
+        .add("Rations", 10)
+        .add("Gold", 6)
+        .add("Chest", 2)
+        .add("Barrel", 3)
         .add("Fireball Scroll", 2 + map_depth)
         .add("Confusion Scroll", 2 + map_depth)
         .add("Magic Missile Scroll", 4)
+        .add("Teleport Scroll", 2)
+        .add("Identify Scroll", 3)
+        .add("Remove Curse Scroll", 2)
+        .add("Frost Scroll", 1 + map_depth)
         .add("Dagger", 3)
         .add("Shield", 3)
         .add("Longsword", map_depth - 1)
-        .add("Tower Shield", map_depth - 1);
+        .add("Tower Shield", map_depth - 1)
+        .add("Goblin Archer", map_depth - 1)
+        .add("Sling", 2)
+        .add("Shortbow", 2)
+        .add("Crossbow", map_depth - 2)
+        .add("Sling Stones", 3)
+        .add("Arrows", 3)
+        .add("Crossbow Bolts", map_depth - 2)
+        .add("Greatsword", map_depth - 2)
+        .add("Leather Cap", 2)
+        .add("Iron Helm", map_depth - 2)
+        .add("Leather Armor", 2)
+        .add("Chain Mail", map_depth - 2)
+        .add("Leather Gloves", 2)
+        .add("Leather Boots", 2)
+        .add("Ring of Protection", map_depth - 1)
+        .add("Ring of Might", map_depth - 1)
+        .add("Ring of Fire Resistance", map_depth - 1)
+        .add("Ring of Cold Resistance", map_depth - 1)
+        .add("Amulet of Warding", map_depth - 2)
+        .add("Slow Scroll", 2)
+        .add("Hold Monster Scroll", map_depth)
+        .add("Potion of Haste", 2)
+        .add("Potion of Regeneration", 2)
+        .add("Potion of Strength", 2)
+        .add("Poison Potion", 2)
+        .add("Blindness Potion", 1)
+}
+
+fn shop_table(map_depth : i32) -> RandomTable {
+    RandomTable::new()
+        .add("Health Potion", 6)
+        .add("Rations", 6)
+        .add("Identify Scroll", 3)
+        .add("Remove Curse Scroll", 2)
+        .add("Magic Missile Scroll", 3)
+        .add("Fireball Scroll", map_depth)
+        .add("Teleport Scroll", 2)
+        .add("Arrows", 3)
+        .add("Sling Stones", 3)
+        .add("Crossbow Bolts", map_depth - 2)
+        .add("Dagger", 2)
+        .add("Shield", 2)
+        .add("Longsword", map_depth - 1)
+        .add("Shortbow", 2)
+        .add("Crossbow", map_depth - 2)
+        .add("Leather Cap", 2)
+        .add("Iron Helm", map_depth - 2)
+        .add("Leather Armor", 2)
+        .add("Chain Mail", map_depth - 2)
+}
+
+/// A shopkeeper stands in the middle of the room with their wares in their pack
+pub fn spawn_shop(ecs : &mut World, room : &Rect, map_depth : i32) {
+    let (x, y) = room.center();
+    let vendor = shopkeeper(ecs, x, y);
+
+    let stock : Vec<String> = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let table = shop_table(map_depth);
+        let count = rng.roll_dice(1, 4) + 4;
+        (0 .. count).map(|_| table.roll(&mut rng)).filter(|name| name != "None").collect()
+    };
+    for name in stock.iter() {
+        spawn_carried(ecs, vendor, name);
+    }
+}
+
+/// Create whatever goes by this name straight into an owner's pack. The item
+/// builders all put things on the map, so it is made at the owner's feet and
+/// then packed.
+pub fn spawn_carried(ecs : &mut World, owner : Entity, name : &str) {
+    let (x, y) = ecs.read_storage::<Position>().get(owner).map_or((0, 0), |pos| (pos.x, pos.y));
+    if let Some(item) = spawn_entity(ecs, name, x, y) {
+        give_item(ecs, item, owner);
+    }
 }
 
 /// Fill room with stuff
@@ -278,19 +1524,207 @@ pub fn spawn_room(ecs : &mut World, room : &Rect, map_depth : i32) {
     for spawn in spawn_points.iter() {
         let x = (*spawn.0 % MAPWIDTH) as i32;
         let y = (*spawn.0 / MAPWIDTH) as i32;
+        // Chests are stocked for the depth they are found at
+        match spawn.1.as_str() {
+            "Chest" => { chest(ecs, x, y, map_depth); }
+            name => { spawn_entity(ecs, name, x, y); }
+        }
+    }
+}
 
-        match spawn.1.as_ref() {
-            "Goblin" => goblin(ecs, x, y),
-            "Orc" => orc(ecs, x, y),
-            "Health Potion" => health_potion(ecs, x, y),
-            "Fireball Scroll" => fireball_scroll(ecs, x, y),
-            "Confusion Scroll" => confusion_scroll(ecs, x, y),
-            "Magic Missile Scroll" => magic_missile_scroll(ecs, x, y),
-            "Dagger" => dagger(ecs, x, y),
-            "Shield" => shield(ecs, x, y),
-            "Longsword" => longsword(ecs, x, y),
-            "Tower Shield" => tower_shield(ecs, x, y),
-            _ => {}
+/// Populate a freshly built level. Boss arenas get their hand-placed
+/// inhabitants; other levels a random mix plus any unique due at this depth.
+pub fn spawn_level(ecs : &mut World, map : &Map) {
+    {
+        let mut progress = ecs.write_resource::<RunProgress>();
+        progress.deepest_depth = i32::max(progress.deepest_depth, map.depth);
+    }
+
+    if let Some(arena) = arena::arena_at(map.depth) {
+        for (x, y, cell) in arena.cells(map.width, map.height) {
+            if cell == 'B' {
+                spawn_unique(ecs, arena.boss, x, y);
+            } else if let Some((_cell, guard)) = arena.guards.iter().find(|(glyph, _guard)| *glyph == cell) {
+                spawn_entity(ecs, guard, x, y);
+            }
         }
+        return;
+    }
+
+    for (i, room) in map.rooms.iter().enumerate().skip(1) {
+        if map.shop_rooms.contains(&i) {
+            spawn_shop(ecs, room, map.depth);
+        } else {
+            spawn_room(ecs, room, map.depth);
+            spawn_traps(ecs, room, map.depth);
+        }
+    }
+
+    // Uniques don't set up in the shop
+    let lairs : Vec<usize> = (1 .. map.rooms.len()).filter(|i| !map.shop_rooms.contains(i)).collect();
+    for (name, depth) in UNIQUES.iter() {
+        if *depth == map.depth && !lairs.is_empty() {
+            let room = {
+                let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+                map.rooms[lairs[rng.range(0, lairs.len() as i32) as usize]]
+            };
+            let (x, y) = room.center();
+            spawn_unique(ecs, name, x, y);
+        }
+    }
+}
+
+/// Uniques appear at most once per run, even if their level is built again
+fn spawn_unique(ecs : &mut World, name : &str, x : i32, y : i32) {
+    {
+        let mut progress = ecs.write_resource::<RunProgress>();
+        if progress.uniques_spawned.iter().any(|spawned| spawned == name) { return; }
+        progress.uniques_spawned.push(name.to_string());
+    }
+    spawn_entity(ecs, name, x, y);
+}
+
+/// Create whatever goes by this name at the given position, returning it
+/// unless the name is unknown
+pub fn spawn_entity(ecs : &mut World, name : &str, x : i32, y : i32) -> Option<Entity> {
+    let entity = match name {
+        "Goblin" => goblin(ecs, x, y),
+        "Orc" => orc(ecs, x, y),
+        "Deer" => deer(ecs, x, y),
+        "Lost Hound" => lost_hound(ecs, x, y),
+        "Goblin Shaman" => goblin_shaman(ecs, x, y),
+        "Cave Spider" => cave_spider(ecs, x, y),
+        "Young Drake" => young_drake(ecs, x, y),
+        "Grizzle the Old Wolf" => grizzle(ecs, x, y),
+        "Skarrg the Goblin King" => goblin_king(ecs, x, y),
+        "Mother of Webs" => mother_of_webs(ecs, x, y),
+        "Vexarith the Ashen Wyrm" => ashen_wyrm(ecs, x, y),
+        "Health Potion" => health_potion(ecs, x, y),
+        "Rations" => rations(ecs, x, y),
+        "Gold" => gold(ecs, x, y),
+        "Barrel" => barrel(ecs, x, y),
+        "Fireball Scroll" => fireball_scroll(ecs, x, y),
+        "Confusion Scroll" => confusion_scroll(ecs, x, y),
+        "Magic Missile Scroll" => magic_missile_scroll(ecs, x, y),
+        "Teleport Scroll" => teleport_scroll(ecs, x, y),
+        "Identify Scroll" => identify_scroll(ecs, x, y),
+        "Remove Curse Scroll" => remove_curse_scroll(ecs, x, y),
+        "Frost Scroll" => frost_scroll(ecs, x, y),
+        "Dagger" => dagger(ecs, x, y),
+        "Shield" => shield(ecs, x, y),
+        "Longsword" => longsword(ecs, x, y),
+        "Tower Shield" => tower_shield(ecs, x, y),
+        "Goblin Archer" => goblin_archer(ecs, x, y),
+        "Sling" => sling(ecs, x, y),
+        "Shortbow" => shortbow(ecs, x, y),
+        "Crossbow" => crossbow(ecs, x, y),
+        "Sling Stones" => ammunition(ecs, x, y, "Sling Stones", AmmoType::Stone, 15),
+        "Arrows" => ammunition(ecs, x, y, "Arrows", AmmoType::Arrow, 12),
+        "Crossbow Bolts" => ammunition(ecs, x, y, "Crossbow Bolts", AmmoType::Bolt, 8),
+        "Greatsword" => greatsword(ecs, x, y),
+        "Leather Cap" => armor(ecs, x, y, "Leather Cap",
+            RGB::named(rltk::BURLYWOOD), EquipmentSlot::Head, 1),
+        "Iron Helm" => armor(ecs, x, y, "Iron Helm",
+            RGB::named(rltk::SILVER), EquipmentSlot::Head, 2),
+        "Leather Armor" => armor(ecs, x, y, "Leather Armor",
+            RGB::named(rltk::BURLYWOOD), EquipmentSlot::Body, 1),
+        "Chain Mail" => armor(ecs, x, y, "Chain Mail",
+            RGB::named(rltk::SILVER), EquipmentSlot::Body, 3),
+        "Leather Gloves" => armor(ecs, x, y, "Leather Gloves",
+            RGB::named(rltk::BURLYWOOD), EquipmentSlot::Hands, 1),
+        "Leather Boots" => armor(ecs, x, y, "Leather Boots",
+            RGB::named(rltk::BURLYWOOD), EquipmentSlot::Feet, 1),
+        "Ring of Protection" => armor(ecs, x, y, "Ring of Protection",
+            RGB::named(rltk::CYAN), EquipmentSlot::LeftRing, 1),
+        "Ring of Might" => ring_of_might(ecs, x, y),
+        "Ring of Fire Resistance" => resistance_ring(ecs, x, y, "Ring of Fire Resistance",
+            RGB::named(rltk::ORANGE), DamageType::Fire),
+        "Ring of Cold Resistance" => resistance_ring(ecs, x, y, "Ring of Cold Resistance",
+            RGB::named(rltk::LIGHTBLUE), DamageType::Cold),
+        "Slow Scroll" => status_scroll(ecs, x, y, "Slow Scroll", RGB::named(rltk::LIGHTBLUE),
+            InflictsStatus{ kind : StatusKind::Slow, turns : 8, magnitude : 0 }),
+        "Hold Monster Scroll" => status_scroll(ecs, x, y, "Hold Monster Scroll", RGB::named(rltk::WHITE),
+            InflictsStatus{ kind : StatusKind::Paralysis, turns : 3, magnitude : 0 }),
+        "Potion of Haste" => status_potion(ecs, x, y, "Potion of Haste", RGB::named(rltk::YELLOW),
+            InflictsStatus{ kind : StatusKind::Haste, turns : 10, magnitude : 0 }),
+        "Potion of Regeneration" => status_potion(ecs, x, y, "Potion of Regeneration", RGB::named(rltk::GREEN),
+            InflictsStatus{ kind : StatusKind::Regeneration, turns : 10, magnitude : 1 }),
+        "Potion of Strength" => status_potion(ecs, x, y, "Potion of Strength", RGB::named(rltk::RED),
+            InflictsStatus{ kind : StatusKind::StrengthBoost, turns : 20, magnitude : 4 }),
+        "Poison Potion" => status_potion(ecs, x, y, "Poison Potion", RGB::named(rltk::DARKGREEN),
+            InflictsStatus{ kind : StatusKind::Poison, turns : 5, magnitude : 1 }),
+        "Blindness Potion" => status_potion(ecs, x, y, "Blindness Potion", RGB::named(rltk::GREY),
+            InflictsStatus{ kind : StatusKind::Blindness, turns : 8, magnitude : 0 }),
+        "Amulet of Warding" => armor(ecs, x, y, "Amulet of Warding",
+            RGB::named(rltk::GOLD), EquipmentSlot::Amulet, 2),
+        _ => return None
+    };
+    Some(entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ arena::FINAL_DEPTH, test_world };
+
+    /// New games spawn the first level before the Map resource exists, so
+    /// nothing spawned may rely on it
+    #[test]
+    fn every_depth_spawns_without_a_map_resource() {
+        let mut containers = 0;
+        let mut traps = 0;
+        for seed in 0 .. 5 {
+            for depth in 1 ..= FINAL_DEPTH {
+                let mut ecs = test_world();
+                ecs.insert(RandomNumberGenerator::seeded(seed * 100 + depth as u64));
+                let map = Map::new_level(depth);
+                let (x, y) = map.rooms[0].center();
+                player(&mut ecs, x, y);
+                spawn_level(&mut ecs, &map);
+
+                containers += ecs.read_storage::<Container>().count();
+                traps += ecs.read_storage::<Trap>().count();
+            }
+        }
+        assert!(containers > 0);
+        assert!(traps > 0);
+    }
+
+    #[test]
+    fn carried_items_go_straight_into_the_pack() {
+        let mut ecs = test_world();
+        let owner = ecs.create_entity().build();
+        spawn_carried(&mut ecs, owner, "Health Potion");
+        spawn_carried(&mut ecs, owner, "Health Potion");
+
+        let positions = ecs.read_storage::<Position>();
+        let backpack = ecs.read_storage::<InBackpack>();
+        let stacks = ecs.read_storage::<Stackable>();
+        let carried : Vec<&Stackable> = (&backpack, &stacks).join()
+            .filter(|(pack, _stack)| pack.owner == owner)
+            .map(|(_pack, stack)| stack)
+            .collect();
+        assert_eq!(carried.len(), 1);
+        assert_eq!(carried[0].quantity, 2);
+        assert_eq!(positions.count(), 0);
+    }
+
+    #[test]
+    fn packing_an_item_leaves_whatever_lies_nearby() {
+        let mut ecs = test_world();
+        let owner = ecs.create_entity().with(Position{ x : 5, y : 5 }).build();
+        let loose = spawn_entity(&mut ecs, "Health Potion", 5, 5).unwrap();
+        spawn_carried(&mut ecs, owner, "Rations");
+
+        assert!(ecs.read_storage::<Position>().get(loose).is_some());
+        assert!(ecs.read_storage::<InBackpack>().get(loose).is_none());
+        assert_eq!(ecs.read_storage::<InBackpack>().count(), 1);
+    }
+
+    #[test]
+    fn unknown_names_spawn_nothing() {
+        let mut ecs = test_world();
+        assert!(spawn_entity(&mut ecs, "Unicorn", 5, 5).is_none());
+        assert_eq!(ecs.entities().join().count(), 0);
     }
 }
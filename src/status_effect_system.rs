@@ -0,0 +1,113 @@
+use specs::prelude::*;
+use super::{
+    Attributes,
+    DamageType,
+    EffectQueue,
+    EffectType,
+    GameLog,
+    Name,
+    RunState,
+    StatusEffects,
+    StatusKind,
+    Targets,
+    Viewshed
+};
+
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, RunState>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, EffectQueue>,
+                       WriteStorage<'a, StatusEffects>,
+                       WriteStorage<'a, Attributes>,
+                       WriteStorage<'a, Viewshed>,
+                       ReadStorage<'a, Name>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (
+            entities,
+            runstate,
+            player_entity,
+            mut gamelog,
+            mut effects,
+            mut statuses,
+            mut attributes,
+            mut viewsheds,
+            names
+        ) = data;
+
+        // Durations only run down once per round, when the monsters move
+        let tick = *runstate == RunState::MonsterTurn;
+        let mut cleared : Vec<Entity> = Vec::new();
+
+        for (entity, status) in (&entities, &mut statuses).join() {
+            let was_blind = status.has(StatusKind::Blindness);
+
+            if tick {
+                for effect in status.effects.iter_mut() {
+                    match effect.kind {
+                        StatusKind::Poison => {
+                            effects.add(
+                                None,
+                                EffectType::Damage{ amount : effect.magnitude, damage_type : DamageType::Poison },
+                                Targets::Single{ target : entity }
+                            );
+                            if entity == *player_entity {
+                                gamelog.entries.push(format!("You take {} poison damage.", effect.magnitude));
+                            }
+                        }
+                        StatusKind::Regeneration => {
+                            effects.add(None, EffectType::Healing{ amount : effect.magnitude }, Targets::Single{ target : entity });
+                        }
+                        _ => {}
+                    }
+                    effect.turns -= 1;
+
+                    if effect.turns < 1 {
+                        if entity == *player_entity {
+                            gamelog.entries.push(format!("You are no longer {}.", effect.kind.name().to_lowercase()));
+                        } else if effect.kind == StatusKind::Confusion {
+                            if let Some(name) = names.get(entity) {
+                                gamelog.entries.push(format!("{} is no longer confused.", name.name));
+                            }
+                        }
+                    }
+                }
+                status.effects.retain(|effect| effect.turns > 0);
+            }
+
+            // Attribute modifiers are rebuilt from scratch from whatever is still active
+            if let Some(attr) = attributes.get_mut(entity) {
+                attr.might.modifiers = 0;
+                attr.quickness.modifiers = 0;
+                for effect in status.effects.iter() {
+                    match effect.kind {
+                        StatusKind::StrengthBoost => attr.might.modifiers += effect.magnitude,
+                        StatusKind::Haste => attr.quickness.modifiers += 4,
+                        StatusKind::Slow => attr.quickness.modifiers -= 4,
+                        _ => {}
+                    }
+                }
+            }
+
+            // Blind entities see only their own surroundings
+            if was_blind || status.has(StatusKind::Blindness) {
+                if let Some(viewshed) = viewsheds.get_mut(entity) {
+                    viewshed.dirty = true;
+                }
+            }
+
+            if status.effects.is_empty() {
+                cleared.push(entity);
+            }
+        }
+
+        for entity in cleared.iter() {
+            statuses.remove(*entity);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use specs::prelude::*;
+use super::{
+    Attributes,
+    Encumbrance,
+    gamelog::GameLog,
+    GoalItem,
+    identification::display_name,
+    inventory_system::{ give_item, split_stack },
+    max_carry_lbs,
+    Purse,
+    Stackable,
+    Value,
+    Weight
+};
+
+/// What a vendor asks for one of an item
+pub fn buy_price(ecs : &World, item : Entity) -> i32 {
+    ecs.read_storage::<Value>().get(item).map_or(0, |value| value.gold)
+}
+
+/// What a vendor pays for one of an item: half what they would sell it for
+pub fn sell_price(ecs : &World, item : Entity) -> i32 {
+    buy_price(ecs, item) / 2
+}
+
+/// Trade single items off the top of a stack
+fn take_one(ecs : &mut World, item : Entity) -> Entity {
+    let quantity = ecs.read_storage::<Stackable>().get(item).map_or(1, |stack| stack.quantity);
+    if quantity > 1 { split_stack(ecs, item, 1) } else { item }
+}
+
+/// Trading takes no time, so keep the load up to date between turns
+fn carry_weight(ecs : &mut World, carrier : Entity, lbs : f32) {
+    if let Some(burden) = ecs.write_storage::<Encumbrance>().get_mut(carrier) {
+        burden.carried_lbs += lbs;
+    }
+}
+
+/// The player buys one of an item from a vendor's stock
+pub fn buy(ecs : &mut World, item : Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let price = buy_price(ecs, item);
+    let name = display_name(ecs, item);
+
+    let gold = ecs.read_storage::<Purse>().get(player_entity).map_or(0, |purse| purse.gold);
+    if gold < price {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You can't afford the {}.", name));
+        return;
+    }
+
+    let item_lbs = ecs.read_storage::<Weight>().get(item).map_or(0.0, |weight| weight.lbs);
+    let too_heavy = {
+        let attributes = ecs.read_storage::<Attributes>();
+        let encumbrance = ecs.read_storage::<Encumbrance>();
+        match (attributes.get(player_entity), encumbrance.get(player_entity)) {
+            (Some(attr), Some(burden)) => burden.carried_lbs + item_lbs > max_carry_lbs(attr.might.total()),
+            _ => false
+        }
+    };
+    if too_heavy {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You can't carry the {} as well, it's too heavy.", name));
+        return;
+    }
+
+    let bought = take_one(ecs, item);
+    let name = display_name(ecs, bought);
+    give_item(ecs, bought, player_entity);
+    if let Some(purse) = ecs.write_storage::<Purse>().get_mut(player_entity) {
+        purse.gold -= price;
+    }
+    carry_weight(ecs, player_entity, item_lbs);
+    ecs.fetch_mut::<GameLog>().entries.push(format!("You buy the {} for {} gold.", name, price));
+}
+
+/// The player sells one of an item from their pack to a vendor
+pub fn sell(ecs : &mut World, vendor : Entity, item : Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    if ecs.read_storage::<GoalItem>().get(item).is_some() {
+        ecs.fetch_mut::<GameLog>().entries.push("You didn't come all this way to sell that.".to_string());
+        return;
+    }
+
+    let price = sell_price(ecs, item);
+    let item_lbs = ecs.read_storage::<Weight>().get(item).map_or(0.0, |weight| weight.lbs);
+    let sold = take_one(ecs, item);
+    let name = display_name(ecs, sold);
+    give_item(ecs, sold, vendor);
+    if let Some(purse) = ecs.write_storage::<Purse>().get_mut(player_entity) {
+        purse.gold += price;
+    }
+    carry_weight(ecs, player_entity, -item_lbs);
+    ecs.fetch_mut::<GameLog>().entries.push(format!("You sell the {} for {} gold.", name, price));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ InBackpack, spawner, test_world };
+
+    /// A player with some gold and a vendor to trade with
+    fn market(gold : i32) -> (World, Entity, Entity) {
+        let mut ecs = test_world();
+        let player = ecs.create_entity().with(Purse{ gold }).build();
+        let vendor = ecs.create_entity().build();
+        ecs.insert(player);
+        (ecs, player, vendor)
+    }
+
+    /// Rations go for 5 gold apiece
+    fn rations(ecs : &mut World, owner : Entity, quantity : i32) -> Entity {
+        let rations = spawner::spawn_entity(ecs, "Rations", 0, 0).unwrap();
+        ecs.write_storage::<Stackable>().insert(rations, Stackable{ quantity }).unwrap();
+        give_item(ecs, rations, owner);
+        rations
+    }
+
+    fn gold(ecs : &World, owner : Entity) -> i32 {
+        ecs.read_storage::<Purse>().get(owner).unwrap().gold
+    }
+
+    fn carried(ecs : &World, owner : Entity) -> Vec<Entity> {
+        (&ecs.entities(), &ecs.read_storage::<InBackpack>()).join()
+            .filter(|(_item, pack)| pack.owner == owner)
+            .map(|(item, _pack)| item)
+            .collect()
+    }
+
+    #[test]
+    fn vendors_pay_half_what_they_charge() {
+        let (mut ecs, player, _vendor) = market(0);
+        let item = rations(&mut ecs, player, 1);
+        assert_eq!(buy_price(&ecs, item), 5);
+        assert_eq!(sell_price(&ecs, item), 2);
+    }
+
+    #[test]
+    fn buying_takes_one_from_the_stack_and_keeps_its_value() {
+        let (mut ecs, player, vendor) = market(25);
+        let stock = rations(&mut ecs, vendor, 3);
+
+        buy(&mut ecs, stock);
+        assert_eq!(gold(&ecs, player), 20);
+        assert_eq!(ecs.read_storage::<Stackable>().get(stock).unwrap().quantity, 2);
+        let bought = carried(&ecs, player);
+        assert_eq!(bought.len(), 1);
+        assert_eq!(buy_price(&ecs, bought[0]), 5);
+    }
+
+    #[test]
+    fn buying_needs_enough_gold() {
+        let (mut ecs, player, vendor) = market(4);
+        let stock = rations(&mut ecs, vendor, 1);
+
+        buy(&mut ecs, stock);
+        assert_eq!(gold(&ecs, player), 4);
+        assert!(carried(&ecs, player).is_empty());
+    }
+
+    #[test]
+    fn selling_leaves_the_vendor_something_worth_buying_back() {
+        let (mut ecs, player, vendor) = market(0);
+        let pack = rations(&mut ecs, player, 2);
+
+        sell(&mut ecs, vendor, pack);
+        assert_eq!(gold(&ecs, player), 2);
+        let sold = carried(&ecs, vendor);
+        assert_eq!(sold.len(), 1);
+        assert_eq!(buy_price(&ecs, sold[0]), 5);
+    }
+
+    #[test]
+    fn the_amulet_is_not_for_sale() {
+        let (mut ecs, player, vendor) = market(0);
+        let amulet = rations(&mut ecs, player, 1);
+        ecs.write_storage::<GoalItem>().insert(amulet, GoalItem{}).unwrap();
+
+        sell(&mut ecs, vendor, amulet);
+        assert_eq!(gold(&ecs, player), 0);
+        assert!(carried(&ecs, vendor).is_empty());
+    }
+}
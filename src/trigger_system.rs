@@ -0,0 +1,130 @@
+use rltk::Point;
+use specs::prelude::*;
+use super::{
+    aoe_tiles,
+    AreaOfEffect,
+    EffectQueue,
+    EffectType,
+    EntityMoved,
+    EntryTrigger,
+    GameLog,
+    Hidden,
+    Map,
+    Name,
+    Position,
+    Targets
+};
+
+/// Whoever sets a trap off takes the brunt of it, unless it fills an area
+pub fn trap_targets(map : &Map, area : Option<&AreaOfEffect>, victim : Entity, at : Point) -> Targets {
+    match area {
+        None => Targets::Single{ target : victim },
+        Some(area) => Targets::Tiles{ tiles : aoe_tiles(map, at, area.radius) }
+    }
+}
+
+/// Springs anything with an entry trigger on the tiles creatures moved onto
+pub struct TriggerSystem {}
+
+impl<'a> System<'a> for TriggerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, Map>,
+                       ReadExpect<'a, Entity>,
+                       Entities<'a>,
+                       WriteStorage<'a, EntityMoved>,
+                       ReadStorage<'a, Position>,
+                       ReadStorage<'a, EntryTrigger>,
+                       WriteStorage<'a, Hidden>,
+                       ReadStorage<'a, AreaOfEffect>,
+                       ReadStorage<'a, Name>,
+                       WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, EffectQueue>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (map, player_entity, entities, mut moved, positions, triggers, mut hidden, areas, names, mut gamelog, mut effects) = data;
+
+        for (entity, _moved, pos) in (&entities, &moved, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            for trap in map.tile_content[idx].iter() {
+                if *trap == entity || triggers.get(*trap).is_none() { continue; }
+
+                // Once sprung, everyone knows it's there
+                hidden.remove(*trap);
+                let trap_name = names.get(*trap).map_or(String::new(), |n| n.name.clone());
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You trigger a {}!", trap_name));
+                } else if map.visible_tiles[idx] {
+                    let victim = names.get(entity).map_or(String::new(), |n| n.name.clone());
+                    gamelog.entries.push(format!("{} triggers a {}!", victim, trap_name));
+                }
+                effects.add(
+                    Some(*trap),
+                    EffectType::ItemUse{ item : *trap },
+                    trap_targets(&map, areas.get(*trap), entity, Point::new(pos.x, pos.y))
+                );
+            }
+        }
+
+        moved.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+
+    fn open_map() -> Map {
+        let mut map = Map::new_map_test();
+        for y in 1 .. map.height - 1 {
+            for x in 1 .. map.width - 1 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn a_trap_hits_its_victim_unless_it_fills_an_area() {
+        let map = open_map();
+        let mut ecs = crate::test_world();
+        let victim = ecs.create_entity().build();
+        let at = Point::new(10, 10);
+
+        match trap_targets(&map, None, victim, at) {
+            Targets::Single{ target } => assert_eq!(target, victim),
+            _ => panic!("a plain trap should only hit whoever sprang it")
+        }
+        match trap_targets(&map, Some(&AreaOfEffect{ radius : 1 }), victim, at) {
+            Targets::Tiles{ tiles } => assert_eq!(tiles.len(), 9),
+            _ => panic!("an area trap should hit the tiles around it")
+        }
+    }
+
+    #[test]
+    fn springing_a_trap_reveals_it() {
+        let mut ecs = crate::test_world();
+        let mut map = open_map();
+        let player = ecs.create_entity()
+            .with(Position{ x : 10, y : 10 })
+            .with(EntityMoved{})
+            .build();
+        let trap = ecs.create_entity()
+            .with(Position{ x : 10, y : 10 })
+            .with(Name{ name : "Spike Pit".to_string() })
+            .with(EntryTrigger{})
+            .with(Hidden{})
+            .build();
+        let idx = map.xy_idx(10, 10);
+        map.tile_content[idx] = vec![player, trap];
+        ecs.insert(map);
+        ecs.insert(player);
+
+        TriggerSystem{}.run_now(&ecs);
+
+        assert!(ecs.read_storage::<Hidden>().get(trap).is_none());
+        assert!(ecs.read_storage::<EntityMoved>().get(player).is_none());
+        assert_eq!(ecs.fetch::<GameLog>().entries, vec!["You trigger a Spike Pit!".to_string()]);
+    }
+}
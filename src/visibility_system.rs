@@ -1,26 +1,73 @@
 use specs::prelude::*;
-use super::{ Map, Player, Position, Viewshed };
-use rltk::{field_of_view, Point};
+use super::{
+    Attributes,
+    GameLog,
+    Hidden,
+    Map,
+    Name,
+    Player,
+    Position,
+    Skill,
+    skill_check,
+    Skills,
+    StatusEffects,
+    StatusKind,
+    Trap,
+    Viewshed
+};
+use rltk::{field_of_view, Point, RandomNumberGenerator};
+
+/// Noticing a trap in passing is harder than searching for one
+const PASSIVE_PERCEPTION_PENALTY : i32 = 8;
 
 pub struct VisibilitySystem {}
 
 impl<'a> System<'a> for VisibilitySystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (WriteExpect<'a, Map>,
                        Entities<'a>,
                        WriteStorage<'a, Viewshed>,
                        WriteStorage<'a, Position>,
-                       ReadStorage<'a, Player>);
+                       ReadStorage<'a, Player>,
+                       ReadStorage<'a, StatusEffects>,
+                       WriteExpect<'a, RandomNumberGenerator>,
+                       WriteExpect<'a, GameLog>,
+                       ReadStorage<'a, Skills>,
+                       ReadStorage<'a, Attributes>,
+                       WriteStorage<'a, Hidden>,
+                       ReadStorage<'a, Trap>,
+                       ReadStorage<'a, Name>);
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player) = data;
+        let (
+            mut map,
+            entities,
+            mut viewshed,
+            pos,
+            player,
+            statuses,
+            mut rng,
+            mut gamelog,
+            skills,
+            attributes,
+            mut hidden,
+            traps,
+            names
+        ) = data;
 
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             if viewshed.dirty {
                 viewshed.dirty = false;
                 viewshed.visible_tiles.clear();
+
+                let mut range = viewshed.range;
+                if let Some(status) = statuses.get(ent) {
+                    if status.has(StatusKind::Blindness) { range = 1; }
+                }
+
                 viewshed.visible_tiles = field_of_view(
                     Point::new(pos.x, pos.y),
-                    viewshed.range,
+                    range,
                     &*map
                 );
                 viewshed.visible_tiles.retain(
@@ -35,10 +82,22 @@ impl<'a> System<'a> for VisibilitySystem {
                         let idx = map.xy_idx(vis.x, vis.y);
                         map.revealed_tiles[idx] = true;
                         map.visible_tiles[idx] = true;
+
+                        // Every hidden trap in sight gets a chance to be noticed
+                        for entity in map.tile_content[idx].iter() {
+                            if hidden.get(*entity).is_none() { continue; }
+                            if let Some(trap) = traps.get(*entity) {
+                                let difficulty = trap.difficulty + PASSIVE_PERCEPTION_PENALTY;
+                                if skill_check(&mut rng, skills.get(ent), attributes.get(ent), Skill::Perception, difficulty) {
+                                    let name = names.get(*entity).map_or(String::new(), |n| n.name.clone());
+                                    gamelog.entries.push(format!("You spot a {}.", name));
+                                    hidden.remove(*entity);
+                                }
+                            }
+                        }
                     }
                 }
             }
         }
     }
 }
-